@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use crate::elide;
+
+/// Appends the packages and versions that the dependencies referred to on a
+/// line resolve to, such as `/* from tokio 1.38 */`, to every `use`
+/// statement importing from one and every line of code with a path into one,
+/// such as `tokio::sync::Mutex`.
+pub fn annotate_deps(source: &str, dependencies: &BTreeMap<String, String>) -> String {
+    let literals = elide::literals(source);
+    let mut roots = elide::path_roots(source).into_iter().peekable();
+    let mut annotated = String::with_capacity(source.len());
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\r', '\n']);
+        let ending = &line[content.len()..];
+        let end = offset + content.len();
+        offset += line.len();
+        let mut origins = Vec::new();
+        while let Some((_, krate)) = roots.next_if(|(at, _)| *at < offset) {
+            if let Some(origin) = dependencies.get(krate) {
+                if !origins.contains(&origin) {
+                    origins.push(origin);
+                }
+            }
+        }
+        if let Some(krate) = used_crate(content) {
+            origins = dependencies.get(krate).into_iter().collect();
+        }
+        annotated.push_str(content);
+        // Not inside a string spanning several lines.
+        let in_literal = literals
+            .iter()
+            .any(|range| range.start < end && end < range.end);
+        if !origins.is_empty() && !in_literal {
+            let origins = origins.iter().map(|o| o.as_str()).collect::<Vec<_>>();
+            annotated.push_str(" /* from ");
+            annotated.push_str(&origins.join(", "));
+            annotated.push_str(" */");
+        }
+        annotated.push_str(ending);
+    }
+    annotated
+}
+
+/// Returns the crate a line starting a `use` statement imports from.
+pub fn used_crate(line: &str) -> Option<&str> {
+    let (visibility, path) = line.trim_start().split_once("use ")?;
+    if !visibility.is_empty() && !visibility.starts_with("pub") {
+        return None;
+    }
+    let path = path.trim_start().trim_start_matches("::");
+    let end = path
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(path.len());
+    Some(&path[..end]).filter(|krate| !krate.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependencies() -> BTreeMap<String, String> {
+        [("serde", "serde 1.0"), ("tokio", "tokio 1.38")]
+            .into_iter()
+            .map(|(krate, origin)| (krate.to_string(), origin.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn uses_are_annotated() {
+        let source = "use tokio::sync::Mutex;\r\npub use ::serde::Serialize;\nuse crate::dump;\n";
+        assert_eq!(
+            annotate_deps(source, &dependencies()),
+            "use tokio::sync::Mutex; /* from tokio 1.38 */\r\n\
+             pub use ::serde::Serialize; /* from serde 1.0 */\n\
+             use crate::dump;\n"
+        );
+    }
+
+    #[test]
+    fn paths_in_code_are_annotated() {
+        let source = "\
+#[derive(serde::Serialize)]
+struct State {
+    lock: tokio::sync::Mutex<Vec<u8>>,
+}
+fn run(value: ::serde_json::Value) -> Vec::<u8>::Output {
+    tokio::spawn(serde::de::ignore(crate::tokio::f()));
+}
+";
+        assert_eq!(
+            annotate_deps(source, &dependencies()),
+            "\
+#[derive(serde::Serialize)] /* from serde 1.0 */
+struct State {
+    lock: tokio::sync::Mutex<Vec<u8>>, /* from tokio 1.38 */
+}
+fn run(value: ::serde_json::Value) -> Vec::<u8>::Output {
+    tokio::spawn(serde::de::ignore(crate::tokio::f())); /* from tokio 1.38, serde 1.0 */
+}
+"
+        );
+    }
+
+    #[test]
+    fn comments_and_strings_are_left_alone() {
+        let source = "// See tokio::spawn.\nlet s = \"serde::Serialize\n tokio::spawn\";\n";
+        assert_eq!(annotate_deps(source, &dependencies()), source);
+    }
+}
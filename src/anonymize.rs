@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::project::Project;
+
+/// Suffixes of host names which are only reachable inside an organization.
+const INTERNAL_SUFFIXES: &[&str] = &[
+    ".internal",
+    ".local",
+    ".localdomain",
+    ".corp",
+    ".lan",
+    ".intranet",
+];
+
+/// Replaces identifying strings of the machine and organization by
+/// placeholders, keeping the mapping in a file so that answers referring to
+/// the placeholders can be translated back.
+pub struct Anonymizer {
+    path: PathBuf,
+    /// Originals mapped to their placeholders.
+    mapping: BTreeMap<String, String>,
+    /// The user name, which is only replaced in paths and e-mail addresses
+    /// since it is often an ordinary word.
+    user: Option<String>,
+    /// The host name, which is only replaced as a whole word.
+    host: Option<String>,
+}
+
+impl Anonymizer {
+    /// Loads the mapping saved at `path` and extends it with the home
+    /// directory, user and host name, the `roots` of the dumped projects and
+    /// the given `names` of crates or companies.
+    pub fn load(path: PathBuf, roots: &[&Path], names: &[String]) -> Result<Anonymizer, Error> {
+        let mapping = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok()
+            .filter(|user| !user.is_empty());
+        let host = hostname();
+        let mut anonymizer = Anonymizer {
+            path,
+            mapping,
+            user: user.clone(),
+            host: host.clone(),
+        };
+        let user = user.map(|user| anonymizer.map(&user, "user"));
+        for (i, root) in roots.iter().enumerate() {
+            let placeholder = format!("/project{}", i + 1);
+            anonymizer
+                .mapping
+                .entry(root.display().to_string())
+                .or_insert(placeholder);
+        }
+        if let Some(home) = dirs::home_dir() {
+            let placeholder = format!("/home/{}", user.as_deref().unwrap_or("user"));
+            anonymizer
+                .mapping
+                .entry(home.display().to_string())
+                .or_insert(placeholder);
+        }
+        if let Some(host) = host {
+            anonymizer.map(&host, "host");
+        }
+        for name in names {
+            anonymizer.add_name(name);
+        }
+        Ok(anonymizer)
+    }
+
+    /// Adds placeholders for `name` as it appears in crate names and
+    /// identifiers, with the casing of each variant.
+    fn add_name(&mut self, name: &str) {
+        let variants = [
+            name.to_string(),
+            name.replace('-', "_"),
+            name.to_lowercase(),
+            name.to_uppercase(),
+            capitalize(&name.to_lowercase()),
+            name.split(['-', '_'])
+                .map(|word| capitalize(&word.to_lowercase()))
+                .collect(),
+        ];
+        let base = self.map(name, "name");
+        for variant in variants {
+            if self.mapping.contains_key(&variant) {
+                continue;
+            }
+            let cased = |placeholder: &str| {
+                if variant.chars().all(|c| !c.is_lowercase()) {
+                    placeholder.to_uppercase()
+                } else if variant.starts_with(char::is_uppercase) {
+                    capitalize(placeholder)
+                } else {
+                    placeholder.to_string()
+                }
+            };
+            // Each placeholder stands for a single original, so that it can be restored.
+            let mut placeholder = cased(&base);
+            for suffix in 'b'..='z' {
+                if !self.mapping.values().any(|p| *p == placeholder) {
+                    break;
+                }
+                placeholder = cased(&format!("{base}{suffix}"));
+            }
+            self.mapping.insert(variant, placeholder);
+        }
+    }
+
+    /// Returns the placeholder of `original`, adding one starting with
+    /// `prefix` if it has none yet.
+    fn map(&mut self, original: &str, prefix: &str) -> String {
+        if let Some(placeholder) = self.mapping.get(original) {
+            return placeholder.clone();
+        }
+        let n = self
+            .mapping
+            .values()
+            .filter(|p| {
+                p.strip_prefix(prefix)
+                    .is_some_and(|n| n.parse::<usize>().is_ok())
+            })
+            .count();
+        let placeholder = format!("{prefix}{}", n + 1);
+        self.mapping
+            .insert(original.to_string(), placeholder.clone());
+        placeholder
+    }
+
+    /// Replaces the known identifying strings and the hosts of internal URLs in `text`.
+    pub fn apply(&mut self, text: &str) -> String {
+        for host in internal_hosts(text) {
+            let n = self
+                .mapping
+                .values()
+                .filter(|p| p.ends_with(".example"))
+                .count();
+            let placeholder = format!("internal{}.example", n + 1);
+            self.mapping.entry(host).or_insert(placeholder);
+        }
+        let mut text = text.to_string();
+        // Longer originals first, so that a path is replaced before the user name in it.
+        let mut mapping = self.mapping.iter().collect::<Vec<_>>();
+        mapping.sort_by_key(|(original, _)| std::cmp::Reverse(original.len()));
+        for (original, placeholder) in mapping {
+            if Some(original) == self.user.as_ref() {
+                for (before, after) in [("/", "/"), ("\\", "\\"), ("", "@")] {
+                    text = text.replace(
+                        &format!("{before}{original}{after}"),
+                        &format!("{before}{placeholder}{after}"),
+                    );
+                }
+            } else if Some(original) == self.host.as_ref() {
+                text = replace_word(&text, original, placeholder);
+            } else {
+                text = text.replace(original.as_str(), placeholder);
+            }
+        }
+        text
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.mapping)?)
+    }
+}
+
+/// Where the mapping of the placeholders of `project` is kept.
+pub fn mapping_path(project: &Project) -> PathBuf {
+    project.root.join(".cargo-gpt").join("anonymize.json")
+}
+
+/// Restores the originals of the placeholders saved at `path` in standard
+/// input, such as an answer to an anonymized dump.
+pub fn restore(path: &Path, writer: &mut impl Write) -> Result<(), Error> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::other(format!("{}: {e}", path.display())))?;
+    let mapping: BTreeMap<String, String> = serde_json::from_str(&text)?;
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    writer.write_all(restored(&mapping, input).as_bytes())
+}
+
+/// Returns `text` with the placeholders of `mapping` replaced by their originals.
+fn restored(mapping: &BTreeMap<String, String>, mut text: String) -> String {
+    // Longer placeholders first, so that `name10` is not restored as `name1` and a `0`.
+    let mut mapping = mapping.iter().collect::<Vec<_>>();
+    mapping.sort_by_key(|(_, placeholder)| std::cmp::Reverse(placeholder.len()));
+    for (original, placeholder) in mapping {
+        text = text.replace(placeholder.as_str(), original);
+    }
+    text
+}
+
+/// Returns the hosts of URLs in `text` which look internal to an organization.
+fn internal_hosts(text: &str) -> Vec<String> {
+    text.match_indices("://")
+        .map(|(i, _)| {
+            let rest = &text[i + 3..];
+            let rest = rest.split_once('@').map_or(rest, |(userinfo, host)| {
+                // `@` after the host belongs to the path or to other text.
+                if userinfo.contains(|c: char| c == '/' || c.is_whitespace()) {
+                    rest
+                } else {
+                    host
+                }
+            });
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '-'))
+                .unwrap_or(rest.len());
+            &rest[..end]
+        })
+        .filter(|host| {
+            INTERNAL_SUFFIXES
+                .iter()
+                .any(|suffix| host.ends_with(suffix))
+                || is_private_ip(host)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Replaces the occurrences of `word` in `text` which are not part of a
+/// longer word, such as `dev` in `dev-dependencies`.
+fn replace_word(text: &str, word: &str, replacement: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let mut replaced = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (i, _) in text.match_indices(word) {
+        let end = i + word.len();
+        if i < copied || text[..i].ends_with(is_word) || text[end..].starts_with(is_word) {
+            continue;
+        }
+        replaced.push_str(&text[copied..i]);
+        replaced.push_str(replacement);
+        copied = end;
+    }
+    replaced.push_str(&text[copied..]);
+    replaced
+}
+
+fn is_private_ip(host: &str) -> bool {
+    let Ok(ip) = host.parse::<std::net::Ipv4Addr>() else {
+        return false;
+    };
+    ip.is_private() || ip.is_link_local()
+}
+
+fn hostname() -> Option<String> {
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())?;
+    Some(host.trim().to_string()).filter(|host| !host.is_empty())
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn names_are_restored_in_every_casing() {
+        let mut anonymizer = Anonymizer {
+            path: PathBuf::new(),
+            mapping: BTreeMap::new(),
+            user: None,
+            host: None,
+        };
+        anonymizer.add_name("acme-corp");
+        let text = "use acme_corp::AcmeCorp;\n\
+                    // Acme-corp builds acme-corp for ACME-CORP, see http://build.corp/x\n";
+        let anonymized = anonymizer.apply(text);
+        assert!(!anonymized.to_lowercase().contains("acme"));
+        assert!(!anonymized.contains("build.corp"));
+        let placeholders = anonymizer.mapping.values().collect::<HashSet<_>>();
+        assert_eq!(placeholders.len(), anonymizer.mapping.len());
+        assert_eq!(restored(&anonymizer.mapping, anonymized), text);
+    }
+}
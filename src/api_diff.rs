@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use crate::cli::ApiDiffArgs;
+use crate::cli::Args;
+use crate::dump;
+use crate::elide;
+use crate::elide::ItemKind;
+use crate::project::Project;
+use crate::remote::Checkout;
+
+pub fn run(
+    args: &ApiDiffArgs,
+    global: &Args,
+    dir: &Path,
+    project: &Project,
+    writer: &mut impl Write,
+) -> Result<(), Error> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::other("api-diff must be run inside a git repository"));
+    }
+    let toplevel = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let checkout = Checkout::fetch(&toplevel, &args.baseline)?;
+    let baseline_dir = checkout.0.join(dump::relative(dir, Path::new(&toplevel)));
+    let baseline = surface(&Project::load(&baseline_dir, global)?, global)?;
+    let current = surface(project, global)?;
+
+    writeln!(
+        writer,
+        "I am about to release a new version of my crate. Below are the changes to its public API \
+         since {}. Review them with respect to semantic versioning: point out breaking changes, \
+         tell me which version bump they require, and suggest ways to avoid unnecessary breakage.",
+        args.baseline
+    )?;
+    writeln!(writer, "// Removed")?;
+    for (path, signature) in &baseline {
+        if !current.contains_key(path) {
+            writeln!(writer, "- {path}\n    {signature}")?;
+        }
+    }
+    writeln!(writer, "// Added")?;
+    for (path, signature) in &current {
+        if !baseline.contains_key(path) {
+            writeln!(writer, "+ {path}\n    {signature}")?;
+        }
+    }
+    writeln!(writer, "// Changed")?;
+    for (path, signature) in &current {
+        match baseline.get(path) {
+            Some(old) if old != signature => {
+                writeln!(writer, "~ {path}\n  - {old}\n  + {signature}")?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Maps the paths of the public items of the project to their signatures,
+/// with whitespace and comments normalized away.
+fn surface(project: &Project, args: &Args) -> Result<BTreeMap<String, String>, Error> {
+    let mut surface = BTreeMap::new();
+    for file in dump::project_files(project, args) {
+        if file.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let source = std::fs::read_to_string(&file)?;
+        let header = project.header_path(&file);
+        // The end of the last trait, whose items are part of its signature.
+        let mut end = 0;
+        for item in elide::items(&source) {
+            if item.signature.start < end || !item.public {
+                continue;
+            }
+            let signature = &source[item.signature.clone()];
+            let signature = if item.kind == ItemKind::Trait {
+                end = item.range.end;
+                elide::elide_bodies(signature, elide::MARKER, |_| false)
+            } else {
+                signature.to_string()
+            };
+            let path = format!("{}: {}", header, item.path());
+            surface.insert(path, elide::normalize(&signature));
+        }
+    }
+    Ok(surface)
+}
@@ -0,0 +1,345 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use crate::cli::Args;
+use crate::cli::AsmArgs;
+use crate::config::Config;
+use crate::dump;
+use crate::elide;
+use crate::elide::ItemKind;
+use crate::manifest::Tracked;
+use crate::project::Project;
+
+pub fn run(
+    args: &AsmArgs,
+    global: &Args,
+    config: &Config,
+    dir: &Path,
+    project: &Project,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let (kind, extension) = if args.mir {
+        ("mir", "mir")
+    } else {
+        ("asm", "s")
+    };
+    let package = project
+        .package_of(dir)
+        .or(project.packages.first())
+        .ok_or_else(|| Error::other("No package to emit the code of"))?;
+    let out_dir = project.target_dir.join("cargo-gpt");
+    std::fs::create_dir_all(&out_dir)?;
+    let out = out_dir.join(format!("emitted.{extension}"));
+    let mut command = Command::new("cargo");
+    command
+        .args(["rustc", "--release", "-p", &package.name])
+        .current_dir(dir);
+    match &args.bin {
+        Some(bin) => command.args(["--bin", bin]),
+        None => command.arg("--lib"),
+    };
+    // A single codegen unit makes rustc emit a single file.
+    let output = command
+        .arg("--")
+        .arg(format!("--emit={kind}={}", out.display()))
+        .args(["-C", "codegen-units=1"])
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::other(format!("`cargo rustc` failed:\n{stderr}")));
+    }
+    let emitted = std::fs::read_to_string(&out)?;
+    let functions = if args.mir {
+        mir_functions(&emitted)
+    } else {
+        asm_functions(&emitted)
+    };
+    let matches = |name: &str| {
+        args.functions
+            .iter()
+            .any(|pattern| elide::glob(pattern, name) || elide::glob(pattern, last(name)))
+    };
+    let selected = functions
+        .iter()
+        .filter(|(name, _)| matches(name))
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        return Err(Error::other(format!(
+            "No {kind} was emitted for {}. Generic and inlined functions only \
+             appear where they are instantiated or called.",
+            args.functions.join(", ")
+        )));
+    }
+
+    let view = if args.mir { "MIR" } else { "assembly" };
+    writeln!(
+        writer,
+        "Below are functions of my crate with the {view} generated for them in release mode. \
+         Explain what the compiler did with them, for example whether loops were vectorized \
+         and bounds checks removed, and how to change the source to get better code."
+    )?;
+    for (name, code) in selected {
+        writeln!(writer)?;
+        writeln!(writer, "// {view} of `{name}`")?;
+        writer.write_all(code.as_bytes())?;
+    }
+
+    // The source of the functions, wherever they are defined.
+    writeln!(writer)?;
+    for file in dump::project_files(project, global) {
+        if file.extension().is_some_and(|ext| ext == "rs") {
+            let source = std::fs::read_to_string(&file)?;
+            let extracted = elide::extract(&source, |item| {
+                item.kind == ItemKind::Fn && matches(&item.path())
+            });
+            if !extracted.is_empty() {
+                let header = project.header_path(&file);
+                let template = config.file_header(global.format);
+                writer.section(
+                    &header,
+                    &file,
+                    source.as_bytes(),
+                    &extracted,
+                    |writer, _| {
+                        global
+                            .format
+                            .write_file(template, &header, &extracted, writer)
+                    },
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the last segment of a path such as `<foo::Bar as Baz>::qux`.
+fn last(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Splits assembly into the demangled names of its functions and their
+/// instructions and labels, without assembler directives.
+fn asm_functions(asm: &str) -> Vec<(String, String)> {
+    let mut functions: Vec<(String, String)> = Vec::new();
+    let mut in_function = false;
+    for line in asm.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with(char::is_whitespace) && !trimmed.starts_with('.') {
+            if let Some(symbol) = trimmed.strip_suffix(':') {
+                functions.push((demangle(symbol), String::new()));
+                in_function = true;
+                continue;
+            }
+        }
+        if trimmed == ".cfi_endproc" {
+            in_function = false;
+        }
+        let directive = trimmed.starts_with('.') && !trimmed.ends_with(':');
+        if in_function && !directive && !trimmed.is_empty() {
+            if let Some((_, code)) = functions.last_mut() {
+                code.push_str(line);
+                code.push('\n');
+            }
+        }
+    }
+    functions.retain(|(_, code)| !code.is_empty());
+    functions
+}
+
+/// Splits MIR into the names of its functions and their bodies.
+fn mir_functions(mir: &str) -> Vec<(String, String)> {
+    let mut functions: Vec<(String, String)> = Vec::new();
+    let mut current = None;
+    for line in mir.lines() {
+        if let Some(signature) = line.strip_prefix("fn ") {
+            let name = signature.split('(').next().unwrap_or(signature).trim();
+            functions.push((name.to_string(), String::new()));
+            current = Some(functions.len() - 1);
+        }
+        if let Some(i) = current {
+            functions[i].1.push_str(line);
+            functions[i].1.push('\n');
+        }
+        if line == "}" {
+            current = None;
+        }
+    }
+    functions
+}
+
+/// Demangles a symbol of the legacy Rust mangling scheme, such as
+/// `_ZN5hello3add17h0123456789abcdefE` into `hello::add`. Other symbols are
+/// returned unchanged.
+fn demangle(symbol: &str) -> String {
+    let Some(mut rest) = symbol
+        .strip_prefix("_ZN")
+        .or_else(|| symbol.strip_prefix("__ZN"))
+    else {
+        return symbol.to_string();
+    };
+    let mut segments = Vec::new();
+    while let Some(digits) = rest.find(|c: char| !c.is_ascii_digit()).filter(|&n| n > 0) {
+        let Ok(len) = rest[..digits].parse::<usize>() else {
+            break;
+        };
+        let Some(segment) = rest.get(digits..digits + len) else {
+            break;
+        };
+        rest = &rest[digits + len..];
+        // The hash which makes the symbol unique.
+        if segment.len() == 17 && segment.starts_with('h') && rest == "E" {
+            break;
+        }
+        segments.push(unescape(segment));
+    }
+    if segments.is_empty() {
+        return symbol.to_string();
+    }
+    segments.join("::")
+}
+
+fn unescape(segment: &str) -> String {
+    const ESCAPES: &[(&str, &str)] = &[
+        ("$LT$", "<"),
+        ("$GT$", ">"),
+        ("$RF$", "&"),
+        ("$BP$", "*"),
+        ("$C$", ","),
+        ("$SP$", "@"),
+        ("$u20$", " "),
+        ("$u27$", "'"),
+        ("$u5b$", "["),
+        ("$u5d$", "]"),
+        ("$u7b$", "{"),
+        ("$u7d$", "}"),
+        ("$u7e$", "~"),
+    ];
+    let mut segment = segment
+        .strip_prefix("_$")
+        .map_or(segment.to_string(), |s| format!("${s}"));
+    for (escape, c) in ESCAPES {
+        segment = segment.replace(escape, c);
+    }
+    segment.replace("..", "::")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assembly emitted by rustc 1.95 for x86-64, shortened.
+    const ASM: &str = r#"	.file	"hello.71778bb8998a10af-cgu.0"
+	.section	".text._ZN53_$LT$hello..Counter$u20$as$u20$core..fmt..Display$GT$3fmt17hb378cfd25d1de464E","ax",@progbits
+	.globl	_ZN53_$LT$hello..Counter$u20$as$u20$core..fmt..Display$GT$3fmt17hb378cfd25d1de464E
+	.p2align	4
+	.type	_ZN53_$LT$hello..Counter$u20$as$u20$core..fmt..Display$GT$3fmt17hb378cfd25d1de464E,@function
+_ZN53_$LT$hello..Counter$u20$as$u20$core..fmt..Display$GT$3fmt17hb378cfd25d1de464E:
+	.cfi_startproc
+	subq	$24, %rsp
+	.cfi_def_cfa_offset 32
+	callq	*_RNvNtCsgEmfK2I1SDS_4core3fmt5write@GOTPCREL(%rip)
+	addq	$24, %rsp
+	retq
+.Lfunc_end0:
+	.size	_ZN53_$LT$hello..Counter$u20$as$u20$core..fmt..Display$GT$3fmt17hb378cfd25d1de464E, .Lfunc_end0-_ZN53_$LT$hello..Counter$u20$as$u20$core..fmt..Display$GT$3fmt17hb378cfd25d1de464E
+	.cfi_endproc
+
+	.section	.text._ZN5hello3sum17h3cc243a804a1e12eE,"ax",@progbits
+	.globl	_ZN5hello3sum17h3cc243a804a1e12eE
+	.p2align	4
+	.type	_ZN5hello3sum17h3cc243a804a1e12eE,@function
+_ZN5hello3sum17h3cc243a804a1e12eE:
+	.cfi_startproc
+	testq	%rsi, %rsi
+	je	.LBB2_1
+	xorl	%eax, %eax
+	retq
+.LBB2_1:
+	xorl	%eax, %eax
+	retq
+.Lfunc_end2:
+	.size	_ZN5hello3sum17h3cc243a804a1e12eE, .Lfunc_end2-_ZN5hello3sum17h3cc243a804a1e12eE
+	.cfi_endproc
+
+	.type	.Lanon.89f4d89b66214ff4721dfc170dd745c1.0,@object
+	.section	.rodata.str1.1,"aMS",@progbits,1
+.Lanon.89f4d89b66214ff4721dfc170dd745c1.0:
+	.asciz	"\300"
+	.size	.Lanon.89f4d89b66214ff4721dfc170dd745c1.0, 2
+
+	.ident	"rustc version 1.95.0 (59807616e 2026-04-14)"
+	.section	".note.GNU-stack","",@progbits
+"#;
+
+    /// MIR emitted by rustc 1.95, shortened.
+    const MIR: &str = "\
+// WARNING: This output format is intended for human consumers only
+// and is subject to change without notice. Knock yourself out.
+fn <impl at src/lib.rs:3:10: 3:15>::fmt(_1: &Point, _2: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+    bb0: {
+        return;
+    }
+}
+
+alloc1 (size: 5, align: 1) {
+    50 6f 69 6e 74                                  │ Point
+}
+
+fn origin() -> Point {
+    let mut _0: Point;
+
+    bb0: {
+        _0 = const Point {{ x: 0_u8, y: 0_u64 }};
+        return;
+    }
+}
+";
+
+    #[test]
+    fn asm_is_split_into_demangled_functions() {
+        let functions = asm_functions(ASM);
+        let names = functions
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["<hello::Counter as core::fmt::Display>::fmt", "hello::sum"]
+        );
+        assert_eq!(
+            functions[1].1,
+            "\ttestq\t%rsi, %rsi\n\tje\t.LBB2_1\n\txorl\t%eax, %eax\n\tretq\n\
+             .LBB2_1:\n\txorl\t%eax, %eax\n\tretq\n.Lfunc_end2:\n"
+        );
+        assert!(!functions[0].1.contains(".cfi"));
+        assert!(functions[0].1.contains("callq"));
+    }
+
+    #[test]
+    fn mir_is_split_into_functions() {
+        let functions = mir_functions(MIR);
+        let names = functions
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["<impl at src/lib.rs:3:10: 3:15>::fmt", "origin"]);
+        assert!(functions[0].1.ends_with("        return;\n    }\n}\n"));
+        assert!(!functions[0].1.contains("alloc1"));
+        assert!(functions[1].1.starts_with("fn origin() -> Point {\n"));
+    }
+
+    #[test]
+    fn demangle_handles_legacy_and_other_symbols() {
+        assert_eq!(demangle("_ZN5hello3add17h670e58325e87a3f5E"), "hello::add");
+        assert_eq!(demangle("__ZN5hello3add17h670e58325e87a3f5E"), "hello::add");
+        assert_eq!(
+            demangle("_ZN5hello13Wrap$LT$T$GT$3new17h0123456789abcdefE"),
+            "hello::Wrap<T>::new"
+        );
+        assert_eq!(demangle("main"), "main");
+        assert_eq!(demangle("_ZNx"), "_ZNx");
+        assert_eq!(last("<hello::Counter as core::fmt::Display>::fmt"), "fmt");
+    }
+}
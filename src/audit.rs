@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::cli::Args;
+use crate::doctor;
+use crate::dump;
+use crate::project::Project;
+
+/// The subset of `cargo audit --json` that we use.
+#[derive(Deserialize)]
+struct Report {
+    vulnerabilities: Vulnerabilities,
+    #[serde(default)]
+    warnings: BTreeMap<String, Vec<Finding>>,
+}
+
+#[derive(Deserialize)]
+struct Vulnerabilities {
+    list: Vec<Finding>,
+}
+
+#[derive(Deserialize)]
+struct Finding {
+    advisory: Option<Advisory>,
+    package: AffectedPackage,
+    versions: Option<Versions>,
+}
+
+#[derive(Deserialize)]
+struct Advisory {
+    id: String,
+    title: String,
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct AffectedPackage {
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct Versions {
+    patched: Vec<String>,
+}
+
+/// The subset of the diagnostics `cargo deny --format json check advisories`
+/// writes, one per line, that we use.
+#[derive(Deserialize)]
+struct DenyLine {
+    #[serde(rename = "type")]
+    kind: String,
+    fields: DenyFields,
+}
+
+#[derive(Deserialize)]
+struct DenyFields {
+    advisory: Option<Advisory>,
+    #[serde(default)]
+    graphs: Vec<DenyGraph>,
+}
+
+#[derive(Deserialize)]
+struct DenyGraph {
+    #[serde(rename = "Krate")]
+    krate: Option<AffectedPackage>,
+}
+
+pub fn run(
+    args: &Args,
+    dir: &Path,
+    project: &Project,
+    writer: &mut impl Write,
+) -> Result<(), Error> {
+    let installed =
+        |plugin: &str| doctor::version(Command::new("cargo").args([plugin, "--version"])).is_some();
+    let (tool, findings) = if installed("audit") {
+        ("cargo audit", audit(dir)?)
+    } else if installed("deny") {
+        ("cargo deny", deny(dir)?)
+    } else {
+        return Err(Error::other(
+            "Neither `cargo audit` nor `cargo deny` was found, install one with \
+             `cargo install cargo-audit` or `cargo install cargo-deny`",
+        ));
+    };
+    if findings.is_empty() {
+        writeln!(writer, "`{tool}` found no advisories for the dependencies.")?;
+        return Ok(());
+    }
+
+    writeln!(
+        writer,
+        "`{tool}` reported the advisories below for dependencies of my project. \
+         For each of them, explain how exposed my code is based on where it uses the affected crate, \
+         and how to migrate away from the affected versions or APIs."
+    )?;
+    let files = dump::project_files(project, args)
+        .into_iter()
+        .filter(|file| file.extension().is_some_and(|ext| ext == "rs"))
+        .collect::<Vec<_>>();
+    for finding in &findings {
+        let Some(advisory) = &finding.advisory else {
+            continue;
+        };
+        let package = &finding.package;
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "// {}: {} {}: {}",
+            advisory.id, package.name, package.version, advisory.title
+        )?;
+        if let Some(versions) = &finding.versions {
+            writeln!(
+                writer,
+                "// Patched versions: {}",
+                versions.patched.join(", ")
+            )?;
+        }
+        writeln!(writer, "{}", advisory.description.trim_end())?;
+
+        // The crate names the package is used under in code.
+        let krates = project
+            .packages
+            .iter()
+            .flat_map(|p| &p.dependencies)
+            .filter(|(_, origin)| origin.split(' ').next() == Some(package.name.as_str()))
+            .map(|(krate, _)| krate.as_str())
+            .collect::<Vec<_>>();
+        let mut uses = 0;
+        for file in &files {
+            let source = std::fs::read_to_string(file)?;
+            for (i, line) in source.lines().enumerate() {
+                if krates.iter().any(|krate| uses_crate(line, krate)) {
+                    let path = project.header_path(file);
+                    writeln!(writer, "{path}:{}: {}", i + 1, line.trim())?;
+                    uses += 1;
+                }
+            }
+        }
+        if krates.is_empty() {
+            writeln!(writer, "// `{}` is a transitive dependency.", package.name)?;
+        } else if uses == 0 {
+            writeln!(writer, "// No paths into `{}` were found.", package.name)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs `cargo audit` and returns the findings with an advisory.
+fn audit(dir: &Path) -> Result<Vec<Finding>, Error> {
+    // `cargo audit` exits with an error when it finds vulnerabilities, so only
+    // its output tells whether it ran.
+    let output = Command::new("cargo")
+        .args(["audit", "--json"])
+        .current_dir(dir)
+        .output()?;
+    let report: Report = serde_json::from_slice(&output.stdout).map_err(|e| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Error::other(format!("`cargo audit` failed ({e}):\n{stderr}"))
+    })?;
+    Ok(report
+        .vulnerabilities
+        .list
+        .into_iter()
+        .chain(report.warnings.into_values().flatten())
+        .filter(|finding| finding.advisory.is_some())
+        .collect())
+}
+
+/// Runs `cargo deny check advisories` and returns the findings with an
+/// advisory. Its diagnostics do not include the patched versions.
+fn deny(dir: &Path) -> Result<Vec<Finding>, Error> {
+    let output = Command::new("cargo")
+        .args(["deny", "--format", "json", "check", "advisories"])
+        .current_dir(dir)
+        .output()?;
+    // The diagnostics are written to standard error, and the exit status is
+    // an error when there are any.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut findings = Vec::new();
+    let mut diagnostics = 0;
+    for line in stderr.lines() {
+        let Ok(line) = serde_json::from_str::<DenyLine>(line) else {
+            continue;
+        };
+        if line.kind != "diagnostic" {
+            continue;
+        }
+        diagnostics += 1;
+        let package = line.fields.graphs.into_iter().find_map(|graph| graph.krate);
+        if let (Some(advisory), Some(package)) = (line.fields.advisory, package) {
+            findings.push(Finding {
+                advisory: Some(advisory),
+                package,
+                versions: None,
+            });
+        }
+    }
+    if !output.status.success() && diagnostics == 0 {
+        return Err(Error::other(format!("`cargo deny` failed:\n{stderr}")));
+    }
+    Ok(findings)
+}
+
+/// Returns true if `line` contains a path starting with the crate `krate`,
+/// such as `log::info!`, but not one merely ending with it, such as `catalog::`.
+fn uses_crate(line: &str, krate: &str) -> bool {
+    let path = format!("{krate}::");
+    line.match_indices(&path)
+        .any(|(i, _)| !line[..i].ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uses_matches_paths_at_a_boundary() {
+        assert!(uses_crate("log::info!(\"x\");", "log"));
+        assert!(uses_crate("let x = ::log::Level::Info;", "log"));
+        assert!(uses_crate("    f(log::Level::Info)", "log"));
+        assert!(!uses_crate("catalog::load();", "log"));
+        assert!(!uses_crate("my_log::write();", "log"));
+    }
+}
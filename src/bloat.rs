@@ -0,0 +1,170 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::cli::Args;
+use crate::cli::BloatArgs;
+use crate::config::Config;
+use crate::dump;
+use crate::manifest::Tracked;
+use crate::project::Project;
+
+/// The subset of `cargo bloat --message-format json` that we use.
+#[derive(Deserialize)]
+struct Report {
+    #[serde(rename = "file-size")]
+    file_size: u64,
+    #[serde(rename = "text-section-size")]
+    text_section_size: u64,
+    functions: Vec<Function>,
+}
+
+#[derive(Deserialize)]
+struct Function {
+    #[serde(rename = "crate")]
+    krate: Option<String>,
+    name: String,
+    size: u64,
+}
+
+pub fn run(
+    args: &BloatArgs,
+    global: &Args,
+    config: &Config,
+    dir: &Path,
+    project: &Project,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let output = Command::new("cargo")
+        .args(["bloat", "--release", "--message-format", "json", "-n"])
+        .arg(args.count.to_string())
+        .current_dir(dir)
+        .output()
+        .map_err(|e| {
+            Error::other(format!(
+                "Failed to run `cargo bloat` ({e}), install it with `cargo install cargo-bloat`"
+            ))
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::other(format!("`cargo bloat` failed:\n{stderr}")));
+    }
+    let report: Report = serde_json::from_slice(&output.stdout)?;
+
+    writeln!(
+        writer,
+        "I want to reduce the size of the binary built from the crate below. \
+         These are its largest functions and generic instantiations according to cargo-bloat. \
+         Please suggest concrete changes to the source that would shrink the binary."
+    )?;
+    writeln!(
+        writer,
+        "// cargo bloat (file size: {} bytes, .text size: {} bytes)",
+        report.file_size, report.text_section_size
+    )?;
+    for function in &report.functions {
+        let krate = function.krate.as_deref().unwrap_or("[unknown]");
+        writeln!(writer, "{:>10}  {krate}  {}", function.size, function.name)?;
+    }
+
+    // Only the functions of this crate have source we can include.
+    let crates = project.crates().collect::<Vec<_>>();
+    let idents = report
+        .functions
+        .iter()
+        .filter(|f| f.krate.as_deref().is_some_and(|c| crates.contains(&c)))
+        .filter_map(|f| fn_ident(&f.name))
+        .collect::<Vec<_>>();
+    for file in dump::project_files(project, global) {
+        if file.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+            continue;
+        }
+        let source = std::fs::read_to_string(&file)?;
+        if idents.iter().any(|ident| defines_fn(&source, ident)) {
+            dump::read_file(&file, project, global, config, writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the function name from a demangled path such as
+/// `<foo::Bar as core::fmt::Debug>::fmt` or `foo::baz<u32>`.
+fn fn_ident(path: &str) -> Option<&str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut end = path.len();
+    for (i, c) in path.char_indices() {
+        match c {
+            '<' => {
+                if depth == 0 && i > start {
+                    end = i;
+                }
+                depth += 1;
+            }
+            '>' => depth -= 1,
+            ':' if depth == 0 && path[i..].starts_with("::") => {
+                start = i + 2;
+                end = path.len();
+            }
+            _ => {}
+        }
+    }
+    let ident = path.get(start..end)?;
+    (!ident.is_empty() && ident.chars().all(|c| c.is_alphanumeric() || c == '_')).then_some(ident)
+}
+
+fn defines_fn(source: &str, ident: &str) -> bool {
+    source.match_indices("fn ").any(|(i, _)| {
+        let rest = &source[i + 3..];
+        rest.starts_with(ident)
+            && !rest[ident.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_are_parsed() {
+        let json = r#"{"file-size":4325376,"text-section-size":1372160,"functions":[
+            {"crate":"std","name":"std::rt::lang_start_internal","size":1893},
+            {"crate":"demo","name":"<demo::Parser as core::fmt::Debug>::fmt","size":612},
+            {"name":"[Unknown]","size":48}]}"#;
+        let report: Report = serde_json::from_str(json).unwrap();
+        assert_eq!(report.file_size, 4325376);
+        assert_eq!(report.text_section_size, 1372160);
+        assert_eq!(report.functions.len(), 3);
+        assert_eq!(report.functions[1].krate.as_deref(), Some("demo"));
+        assert_eq!(report.functions[2].krate, None);
+        assert_eq!(report.functions[2].size, 48);
+    }
+
+    #[test]
+    fn function_names_are_extracted() {
+        assert_eq!(fn_ident("demo::main"), Some("main"));
+        assert_eq!(
+            fn_ident("<demo::Parser as core::fmt::Debug>::fmt"),
+            Some("fmt")
+        );
+        assert_eq!(fn_ident("demo::parse::expr<u32>"), Some("expr"));
+        assert_eq!(
+            fn_ident("core::ptr::drop_in_place<alloc::vec::Vec<u8>>"),
+            Some("drop_in_place")
+        );
+        assert_eq!(fn_ident("demo::main::{{closure}}"), None);
+        assert_eq!(fn_ident("[Unknown]"), None);
+    }
+
+    #[test]
+    fn definitions_are_found_by_whole_name() {
+        let source = "fn parse_all() {}\npub fn parse(input: &str) {}\n";
+        assert!(defines_fn(source, "parse"));
+        assert!(defines_fn(source, "parse_all"));
+        assert!(!defines_fn(source, "parse_al"));
+        assert!(!defines_fn(source, "input"));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::elide;
+use crate::tokens;
+
+/// The parts of a dump which are given separate shares of a token budget.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Part {
+    /// The task or question preceding the files.
+    Question,
+    /// Source files.
+    Code,
+    /// Documentation, manifests and other files which are not code.
+    Attachments,
+}
+
+/// The tokens left to each part of a dump, so that an oversized part is
+/// trimmed instead of crowding out the others.
+pub struct Budget {
+    remaining: [usize; 3],
+}
+
+impl Budget {
+    /// Splits `total` tokens proportionally to the `shares` of the question,
+    /// the code and the attachments.
+    pub fn new(total: usize, shares: [u32; 3]) -> Budget {
+        let sum = shares.iter().sum::<u32>().max(1) as usize;
+        Budget {
+            remaining: shares.map(|share| total * share as usize / sum),
+        }
+    }
+
+    /// Returns `content` trimmed to the tokens left to `part`. Function bodies
+    /// of Rust sources are elided with `marker` before lines are cut off.
+    pub fn fit(&mut self, part: Part, content: String, is_rust: bool, marker: &str) -> String {
+        let remaining = &mut self.remaining[part as usize];
+        let mut content = content;
+        if tokens::estimate(&content) > *remaining && is_rust {
+            content = elide::elide_bodies(&content, marker, |_| false);
+        }
+        let estimate = tokens::estimate(&content);
+        if estimate <= *remaining {
+            *remaining -= estimate;
+            return content;
+        }
+        let fitted = truncate(&content, *remaining);
+        *remaining = 0;
+        fitted
+    }
+
+    /// Gives the tokens of `content`, which was fitted but not written, back to `part`.
+    pub fn refund(&mut self, part: Part, content: &str) {
+        self.remaining[part as usize] += tokens::estimate(content);
+    }
+}
+
+/// Keeps the lines of `content` which fit in `budget` tokens, followed by a
+/// note of how many lines were cut off.
+fn truncate(content: &str, budget: usize) -> String {
+    let lines = content.lines().count();
+    let mut kept = String::new();
+    let mut count = 0;
+    // Leaves room for the note.
+    let mut left = budget.saturating_sub(16);
+    for line in content.lines() {
+        let estimate = tokens::estimate(line) + 1;
+        if estimate > left {
+            break;
+        }
+        left -= estimate;
+        kept.push_str(line);
+        kept.push('\n');
+        count += 1;
+    }
+    kept.push_str(&format!(
+        "// ... {} more lines trimmed to fit the token budget\n",
+        lines - count
+    ));
+    kept
+}
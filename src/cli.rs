@@ -0,0 +1,341 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use clap::Subcommand;
+use clap::ValueEnum;
+
+use crate::format::Format;
+
+/// Cargo runs `cargo-gpt gpt <args>`, so the actual arguments live under `gpt`.
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+pub enum Cargo {
+    #[command(version, about)]
+    Gpt(Args),
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Report more progress on stderr, once for steps and twice for every file.
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Report nothing on stderr, including the output of `cargo` itself.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Report progress on stderr as JSON lines.
+    #[arg(long, global = true)]
+    pub log_json: bool,
+    /// Refuse anything that reaches the network and dump only files in the allowed directories.
+    #[arg(long, global = true)]
+    pub paranoid: bool,
+    /// A directory files may be dumped from in paranoid mode.
+    #[arg(long = "allow", value_name = "DIR", global = true)]
+    pub allowed_dirs: Vec<PathBuf>,
+    /// How to delimit the files of the dump.
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub format: Format,
+    /// Write a JSON manifest mapping each file section of the dump to its source.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub manifest: Option<PathBuf>,
+    /// Write paths in the manifest relative to the current directory, so that
+    /// it is the same on every machine.
+    #[arg(long, global = true)]
+    pub reproducible: bool,
+    /// Dump a tree of the files with only the manifests, `lib.rs`, `main.rs` and `mod.rs`
+    /// files, without running cargo or parsing the sources.
+    #[arg(long, conflicts_with_all = ["roots", "interactive"])]
+    pub quick: bool,
+    /// Another project to include in the dump, with headers prefixed by its name.
+    #[arg(long = "root", value_name = "DIR")]
+    pub roots: Vec<PathBuf>,
+    /// Include path dependencies which are not workspace members.
+    #[arg(long, global = true)]
+    pub path_deps: bool,
+    /// Convert CRLF line endings of files to LF, such as those of a Windows checkout.
+    #[arg(long, global = true)]
+    pub normalize_newlines: bool,
+    /// Include JSON, YAML and CSV files, lockfiles and images, truncated or
+    /// replaced by a summary.
+    #[arg(long, global = true)]
+    pub data: bool,
+    /// Include the manifests of the workspace root and of all path dependencies.
+    #[arg(long, global = true)]
+    pub toml: bool,
+    /// Include markdown design documents: `docs/`, `book/src/`, decision records and
+    /// markdown files at the project root.
+    #[arg(long, global = true)]
+    pub docs: bool,
+    /// Trim markdown files to their headings and the first paragraph below each.
+    #[arg(long, global = true)]
+    pub docs_outline: bool,
+    /// Include code generated into `OUT_DIR` by build scripts (runs `cargo check`).
+    #[arg(long, global = true)]
+    pub out_dir: bool,
+    /// Elide the bodies of the N largest functions, as listed by `stats --functions`.
+    #[arg(long, global = true, value_name = "N")]
+    pub elide_largest: Option<usize>,
+    /// Include the definitions and documentation of the traits of dependencies
+    /// which the project implements, such as `serde::Serialize`.
+    #[arg(long, global = true)]
+    pub foreign_traits: bool,
+    /// Include snippets from across the crate where these types are constructed
+    /// or matched on.
+    #[arg(long, value_delimiter = ',', value_name = "TYPES", global = true)]
+    pub examples: Vec<String>,
+    /// The number of usage snippets included per type with `--examples`.
+    #[arg(long, global = true, value_name = "N", default_value_t = 3)]
+    pub examples_count: usize,
+    /// Include the sources of git submodules, with function bodies elided and
+    /// markdown trimmed to an outline unless `--submodules full` is given.
+    #[arg(
+        long,
+        global = true,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "outline"
+    )]
+    pub submodules: Option<Inclusion>,
+    /// Include vendored code, git submodules and nested package checkouts, in
+    /// full unless `--include-vendored outline` is given.
+    #[arg(
+        long,
+        global = true,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "full"
+    )]
+    pub include_vendored: Option<Inclusion>,
+    /// Annotate `use` statements with the package and version of the dependency they import from.
+    #[arg(long, global = true)]
+    pub annotate_deps: bool,
+    /// Include the code generated by derives and other macros for these items
+    /// beside their definitions (expands the crate with rustc).
+    #[arg(long, value_delimiter = ',', value_name = "NAMES", global = true)]
+    pub expand_derives: Vec<String>,
+    /// Always elide the bodies of the functions matching these patterns, such as `tests::*`.
+    #[arg(long, value_delimiter = ',', value_name = "PATTERNS", global = true)]
+    pub exclude_fn: Vec<String>,
+    /// Always elide the bodies and `impl` blocks of the types matching these patterns,
+    /// such as `Generated*`.
+    #[arg(long, value_delimiter = ',', value_name = "PATTERNS", global = true)]
+    pub exclude_type: Vec<String>,
+    /// Replace the Rust files whose paths match these patterns, such as `src/gen/*`,
+    /// by summaries of their documentation and public items.
+    #[arg(long, value_delimiter = ',', value_name = "PATTERNS", global = true)]
+    pub summarize: Vec<String>,
+    /// Put the prompt of a task before the dump, such as `custom:migrate-async` for the
+    /// template in `.cargo-gpt/tasks/migrate-async.md`.
+    #[arg(long, value_name = "TASK")]
+    pub task: Option<String>,
+    /// Fill a `{{KEY}}` placeholder of the task template instead of asking for it.
+    #[arg(long = "set", value_name = "KEY=VALUE", requires = "task")]
+    pub vars: Vec<String>,
+    /// Trim the dump to about this many tokens, split between the question, the
+    /// code and the attachments as set by `budget-split` in the config.
+    #[arg(long, global = true, value_name = "TOKENS")]
+    pub budget: Option<usize>,
+    /// Mark every item with an anchor such as `// [A17]` and ask for answers to refer to
+    /// them. The manifest maps the anchors to the items.
+    #[arg(long)]
+    pub answer_anchors: bool,
+    /// Ask for the answer in a machine-readable format, which `validate` checks.
+    #[arg(long, value_name = "FORMAT")]
+    pub contract: Option<Contract>,
+    /// Choose files to elide or exclude on the terminal while the dump is over the budget.
+    #[arg(long)]
+    pub interactive: bool,
+    /// Replace absolute paths, the user and host name and internal URLs by
+    /// placeholders, keeping the mapping in `.cargo-gpt/anonymize.json`.
+    #[arg(long)]
+    pub anonymize: bool,
+    /// Also replace these crate or company names when anonymizing.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "NAMES",
+        requires = "anonymize"
+    )]
+    pub anonymize_names: Vec<String>,
+    /// Write the question in `$EDITOR` and put it before the dump.
+    #[arg(long)]
+    pub edit: bool,
+    /// Replace files which are unchanged since an earlier dump of the session by a short stub.
+    #[arg(long)]
+    pub dedupe_session: bool,
+    /// Forget the files sent in earlier dumps and start a new session.
+    #[arg(long)]
+    pub new_session: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Compare the public API with an earlier revision in a semver review prompt.
+    ApiDiff(ApiDiffArgs),
+    /// Bundle the assembly or MIR generated for functions with their source in an optimization prompt.
+    Asm(AsmArgs),
+    /// Explain the security advisories reported by `cargo audit` and where they affect the code.
+    Audit,
+    /// Inspect the configuration.
+    Config(ConfigArgs),
+    /// Restore the names replaced by `--anonymize` in text read from standard input.
+    Deanonymize,
+    /// Check the tools and files cargo-gpt depends on.
+    Doctor,
+    /// Bundle the largest functions of the release binary into a size-reduction prompt.
+    Bloat(BloatArgs),
+    /// Bundle the memory layouts of types with their definitions in a layout review prompt.
+    Layout(LayoutArgs),
+    /// Brief a new contributor on the project: its map, entry points, build and recent commits.
+    Onboard(OnboardArgs),
+    /// Dump a remote git repository instead of the current project.
+    Remote(RemoteArgs),
+    /// Upload an anonymized dump as a secret GitHub gist, or write it to a file, and print its URL.
+    Share(ShareArgs),
+    /// List the largest files, or with `--functions` the largest functions.
+    Stats(StatsArgs),
+    /// Dump summaries of the Rust files, refreshing those cached in `.cargo-gpt/summaries/`.
+    Summarize,
+    /// Elide or extract the items of Rust source read from standard input.
+    Transform(TransformArgs),
+    /// Convert a previously generated dump to another format, optionally in chunks.
+    Reformat(ReformatArgs),
+    /// Check that an answer read from standard input follows the format asked by `--contract`.
+    Validate(ValidateArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Report problems in the config files and show the effective configuration.
+    Check,
+}
+
+#[derive(clap::Args)]
+pub struct AsmArgs {
+    /// Names or paths of the functions, such as `sum` or `Parser::*`.
+    #[arg(required = true)]
+    pub functions: Vec<String>,
+    /// Show MIR instead of assembly.
+    #[arg(long)]
+    pub mir: bool,
+    /// Build this binary instead of the library.
+    #[arg(long, value_name = "NAME")]
+    pub bin: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct LayoutArgs {
+    /// Names or paths of the types, all types of the crate if none are given.
+    pub types: Vec<String>,
+    /// Build this binary instead of the library.
+    #[arg(long, value_name = "NAME")]
+    pub bin: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct ShareArgs {
+    /// Write the dump to this file instead of uploading it.
+    #[arg(long, value_name = "PATH")]
+    pub file: Option<PathBuf>,
+    /// Description of the gist.
+    #[arg(long, default_value = "cargo-gpt prompt")]
+    pub description: String,
+}
+
+#[derive(clap::Args)]
+pub struct OnboardArgs {
+    /// Number of recent commits summarized.
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    pub commits: usize,
+}
+
+#[derive(clap::Args)]
+pub struct ValidateArgs {
+    /// The format the answer was asked to follow.
+    #[arg(long, value_name = "FORMAT")]
+    pub contract: Contract,
+}
+
+#[derive(clap::Args)]
+pub struct StatsArgs {
+    /// List functions by tokens, with their lines and estimated cyclomatic complexity.
+    #[arg(long)]
+    pub functions: bool,
+    /// Number of files or functions to list.
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub count: usize,
+}
+
+#[derive(clap::Args)]
+pub struct BloatArgs {
+    /// Number of functions to list.
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub count: usize,
+}
+
+#[derive(clap::Args)]
+pub struct RemoteArgs {
+    /// URL of the repository to clone.
+    pub url: String,
+    /// Branch, tag or commit to check out.
+    #[arg(long)]
+    pub rev: Option<String>,
+}
+
+#[derive(clap::Args)]
+#[group(required = true, multiple = false)]
+pub struct TransformArgs {
+    /// Replace the bodies of all functions with `{ /* ... */ }`.
+    #[arg(long)]
+    pub elide_all: bool,
+    /// Only output the items with these names or paths, such as `Foo::new` or `tests::*`.
+    #[arg(long, value_delimiter = ',', value_name = "NAMES")]
+    pub only: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct ReformatArgs {
+    /// The dump to convert.
+    pub input: PathBuf,
+    /// Split the output into parts of at most this many (estimated) tokens.
+    #[arg(long, value_name = "TOKENS")]
+    pub chunk_tokens: Option<usize>,
+    /// Write each part to its own `part-N` file in this directory instead of
+    /// to standard output.
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct ApiDiffArgs {
+    /// Git revision of the previous release, such as a tag.
+    #[arg(long)]
+    pub baseline: String,
+}
+
+/// The format an answer is asked to follow.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Contract {
+    /// Unified diffs against the files of the dump.
+    Diff,
+    /// The complete contents of each changed file in a code block.
+    Files,
+    /// A JSON object listing the paths and contents of the changed files.
+    Json,
+}
+
+/// How much of the git submodules or of vendored code to include.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Inclusion {
+    /// Function bodies are elided and markdown is trimmed to its headings.
+    Outline,
+    /// Files are included in full.
+    Full,
+}
@@ -0,0 +1,722 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::cli::Args;
+use crate::elide;
+use crate::format::Format;
+
+/// Settings read from `config.toml` in the global config directory and in
+/// `.cargo-gpt/` of the project, and from `CARGO_GPT_*` environment variables,
+/// where the environment takes precedence over the project, and the project
+/// over the global config.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Replaces elided function bodies. `{lines}` and `{tokens}` are
+    /// substituted by the number of lines and estimated tokens elided.
+    pub elision_marker: Option<String>,
+    /// Templates of the line introducing each file, by format. `{path}` is
+    /// substituted by the path of the file, and `{lines}` and `{tokens}` by
+    /// its size.
+    pub file_header: FileHeaders,
+    /// Whether to always run in `--paranoid` mode.
+    pub paranoid: Option<bool>,
+    /// The only directories files may be dumped from in paranoid mode. Relative
+    /// paths in the project's config are relative to the project.
+    pub allowed_dirs: Option<Vec<PathBuf>>,
+    /// The default of `--budget`.
+    pub budget: Option<usize>,
+    /// The shares of a token budget given to the question, the code and the
+    /// attachments, in percent.
+    pub budget_split: BudgetSplit,
+    /// Patterns of functions whose bodies are always elided, added to `--exclude-fn`.
+    pub exclude_fn: Option<Vec<String>>,
+    /// Patterns of types whose bodies and `impl` blocks are always elided,
+    /// added to `--exclude-type`.
+    pub exclude_type: Option<Vec<String>>,
+    /// External commands the dump is piped through.
+    pub hooks: Hooks,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct FileHeaders {
+    pub plain: Option<String>,
+    pub markdown: Option<String>,
+    pub xml: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct BudgetSplit {
+    pub question: Option<u32>,
+    pub code: Option<u32>,
+    pub attachments: Option<u32>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Hooks {
+    /// Run on the content of every file, which it reads from standard input
+    /// and writes transformed to standard output.
+    pub pre_file: Option<String>,
+    /// Run on the whole output, which it reads from standard input.
+    pub post_dump: Option<String>,
+}
+
+/// A template setting, as checked by `config check`.
+struct Template {
+    key: &'static str,
+    value: Option<String>,
+    default: &'static str,
+    placeholders: &'static [&'static str],
+    /// The format whose files are introduced by the template.
+    format: Option<Format>,
+}
+
+/// All keys of the config file, with tables as prefixes.
+const KEYS: &[&str] = &[
+    "elision-marker",
+    "paranoid",
+    "allowed-dirs",
+    "budget",
+    "budget-split",
+    "budget-split.question",
+    "budget-split.code",
+    "budget-split.attachments",
+    "exclude-fn",
+    "exclude-type",
+    "hooks",
+    "hooks.pre-file",
+    "hooks.post-dump",
+    "file-header",
+    "file-header.plain",
+    "file-header.markdown",
+    "file-header.xml",
+];
+
+impl Config {
+    pub fn load(dir: &Path) -> Result<Config, Error> {
+        let global = match global_path() {
+            Some(path) => read(&path)?,
+            None => Config::default(),
+        };
+        let project = match project_path(dir) {
+            Some(path) => read(&path)?,
+            None => Config::default(),
+        };
+        Ok(env()?.or(project).or(global))
+    }
+
+    /// The template settings of the config.
+    fn templates(&self) -> [Template; 4] {
+        let header = |key, value: &Option<String>, format: Format| Template {
+            key,
+            value: value.clone(),
+            default: format.default_header(),
+            placeholders: &["{path}", "{lines}", "{tokens}"],
+            format: Some(format),
+        };
+        [
+            Template {
+                key: "elision-marker",
+                value: self.elision_marker.clone(),
+                default: elide::MARKER,
+                placeholders: &["{lines}", "{tokens}"],
+                format: None,
+            },
+            header("file-header.plain", &self.file_header.plain, Format::Plain),
+            header(
+                "file-header.markdown",
+                &self.file_header.markdown,
+                Format::Markdown,
+            ),
+            header("file-header.xml", &self.file_header.xml, Format::Xml),
+        ]
+    }
+
+    /// Fills the settings missing from `self` with those of `other`. Paranoid
+    /// mode stays enabled if either enables it.
+    fn or(self, other: Config) -> Config {
+        Config {
+            elision_marker: self.elision_marker.or(other.elision_marker),
+            file_header: FileHeaders {
+                plain: self.file_header.plain.or(other.file_header.plain),
+                markdown: self.file_header.markdown.or(other.file_header.markdown),
+                xml: self.file_header.xml.or(other.file_header.xml),
+            },
+            paranoid: match (self.paranoid, other.paranoid) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (paranoid, other) => paranoid.or(other),
+            },
+            allowed_dirs: self.allowed_dirs.or(other.allowed_dirs),
+            budget: self.budget.or(other.budget),
+            budget_split: BudgetSplit {
+                question: self.budget_split.question.or(other.budget_split.question),
+                code: self.budget_split.code.or(other.budget_split.code),
+                attachments: self
+                    .budget_split
+                    .attachments
+                    .or(other.budget_split.attachments),
+            },
+            exclude_fn: self.exclude_fn.or(other.exclude_fn),
+            exclude_type: self.exclude_type.or(other.exclude_type),
+            hooks: Hooks {
+                pre_file: self.hooks.pre_file.or(other.hooks.pre_file),
+                post_dump: self.hooks.post_dump.or(other.hooks.post_dump),
+            },
+        }
+    }
+
+    /// Makes the relative allowed directories of the project's config file at
+    /// `path` relative to the project.
+    fn resolved(mut self, path: &Path) -> Config {
+        let project = path
+            .parent()
+            .filter(|dir| dir.ends_with(".cargo-gpt"))
+            .and_then(Path::parent);
+        if let (Some(project), Some(dirs)) = (project, &mut self.allowed_dirs) {
+            for dir in dirs {
+                *dir = project.join(&*dir);
+            }
+        }
+        self
+    }
+
+    pub fn paranoid(&self) -> bool {
+        self.paranoid.unwrap_or(false)
+    }
+
+    pub fn allowed_dirs(&self) -> &[PathBuf] {
+        self.allowed_dirs.as_deref().unwrap_or_default()
+    }
+
+    pub fn exclude_fn(&self) -> &[String] {
+        self.exclude_fn.as_deref().unwrap_or_default()
+    }
+
+    pub fn exclude_type(&self) -> &[String] {
+        self.exclude_type.as_deref().unwrap_or_default()
+    }
+
+    /// The shares of the question, the code and the attachments in a token
+    /// budget, 10, 70 and 20 percent unless configured otherwise.
+    pub fn budget_split(&self) -> [u32; 3] {
+        [
+            self.budget_split.question.unwrap_or(10),
+            self.budget_split.code.unwrap_or(70),
+            self.budget_split.attachments.unwrap_or(20),
+        ]
+    }
+
+    pub fn elision_marker(&self) -> &str {
+        self.elision_marker.as_deref().unwrap_or(elide::MARKER)
+    }
+
+    pub fn file_header(&self, format: Format) -> &str {
+        let template = match format {
+            Format::Plain => &self.file_header.plain,
+            Format::Markdown => &self.file_header.markdown,
+            Format::Xml => &self.file_header.xml,
+        };
+        template.as_deref().unwrap_or(format.default_header())
+    }
+}
+
+/// Returns the paths of the global and the project config files that apply to
+/// `dir`, named by their scope, if there can be any.
+pub fn files(dir: &Path) -> [(&'static str, Option<PathBuf>); 2] {
+    [("global", global_path()), ("project", project_path(dir))]
+}
+
+fn global_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("cargo-gpt").join("config.toml"))
+}
+
+/// Returns the path of the config file of the project containing `dir`.
+fn project_path(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(".cargo-gpt").join("config.toml"))
+        .find(|path| path.is_file())
+}
+
+/// Environment variables overriding settings, with the keys they override.
+const ENV: &[(&str, &str)] = &[
+    ("CARGO_GPT_PARANOID", "paranoid"),
+    ("CARGO_GPT_BUDGET", "budget"),
+    ("CARGO_GPT_ELISION_MARKER", "elision-marker"),
+];
+
+/// Returns the settings given by `CARGO_GPT_*` environment variables.
+fn env() -> Result<Config, Error> {
+    let var = |name| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let invalid = |name, value| Error::other(format!("${name}: invalid value `{value}`"));
+    let paranoid = match var("CARGO_GPT_PARANOID") {
+        Some(value) => match value.as_str() {
+            "1" | "true" => Some(true),
+            "0" | "false" => Some(false),
+            _ => return Err(invalid("CARGO_GPT_PARANOID", value)),
+        },
+        None => None,
+    };
+    let budget = match var("CARGO_GPT_BUDGET") {
+        Some(value) => match value.parse() {
+            Ok(budget) => Some(budget),
+            Err(_) => return Err(invalid("CARGO_GPT_BUDGET", value)),
+        },
+        None => None,
+    };
+    Ok(Config {
+        paranoid,
+        budget,
+        elision_marker: var("CARGO_GPT_ELISION_MARKER"),
+        ..Config::default()
+    })
+}
+
+pub fn read(path: &Path) -> Result<Config, Error> {
+    if !path.is_file() {
+        return Ok(Config::default());
+    }
+    let text = std::fs::read_to_string(path)?;
+    let config: Config = toml::from_str(&text).map_err(|e| {
+        Error::other(format!(
+            "{}: {}\nRun `cargo gpt config check` for details.",
+            path.display(),
+            e.to_string().trim_end()
+        ))
+    })?;
+    Ok(config.resolved(path))
+}
+
+/// Reports syntax errors, unknown keys, suspicious templates and patterns and
+/// conflicting settings in the config files that apply to `dir`, the
+/// environment and the options of `args`, followed by the effective
+/// configuration.
+pub fn check(dir: &Path, args: &Args, writer: &mut impl Write) -> Result<(), Error> {
+    let mut problems = 0;
+    // The sources of the settings, by increasing precedence.
+    let mut layers = Vec::new();
+    for (scope, path) in files(dir) {
+        let Some(path) = path.filter(|path| path.is_file()) else {
+            writeln!(writer, "No {scope} config file.")?;
+            continue;
+        };
+        writeln!(
+            writer,
+            "Checking the {scope} config file {}",
+            path.display()
+        )?;
+        let text = std::fs::read_to_string(&path)?;
+        let table = match text.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                writeln!(writer, "{}: {e}", path.display())?;
+                problems += 1;
+                continue;
+            }
+        };
+        for key in keys(&table, "") {
+            if !KEYS.contains(&key.as_str()) {
+                writeln!(
+                    writer,
+                    "{}: unknown key `{key}`",
+                    location(&path, &text, &key)
+                )?;
+                problems += 1;
+            }
+        }
+        let config = match toml::from_str::<Config>(&text) {
+            Ok(config) => config.resolved(&path),
+            Err(e) => {
+                writeln!(writer, "{}: {e}", path.display())?;
+                problems += 1;
+                continue;
+            }
+        };
+        for Template {
+            key,
+            value,
+            placeholders,
+            format,
+            ..
+        } in config.templates()
+        {
+            let Some(template) = value else {
+                continue;
+            };
+            let at = location(&path, &text, key);
+            let mut rest = template.as_str();
+            while let Some(start) = rest.find('{') {
+                let end = rest[start..]
+                    .find('}')
+                    .map_or(rest.len(), |end| start + end + 1);
+                let placeholder = &rest[start..end];
+                let is_word = placeholder[1..placeholder.len() - 1]
+                    .chars()
+                    .all(|c| c.is_ascii_alphabetic());
+                if end > start + 2 && is_word && !placeholders.contains(&placeholder) {
+                    writeln!(
+                        writer,
+                        "{at}: unknown placeholder `{placeholder}` in `{key}`, expected one of {}",
+                        placeholders.join(", ")
+                    )?;
+                    problems += 1;
+                }
+                rest = &rest[end..];
+            }
+            if format.is_some() && !template.contains("{path}") {
+                writeln!(
+                    writer,
+                    "{at}: `{key}` does not contain `{{path}}`, so files cannot be told apart"
+                )?;
+                problems += 1;
+            }
+            if template.contains('\n') {
+                writeln!(writer, "{at}: `{key}` must be a single line")?;
+                problems += 1;
+            }
+        }
+        for (key, patterns) in [
+            ("exclude-fn", config.exclude_fn()),
+            ("exclude-type", config.exclude_type()),
+        ] {
+            for pattern in patterns {
+                if let Some(problem) = pattern_problem(pattern) {
+                    let at = location(&path, &text, key);
+                    writeln!(writer, "{at}: `{pattern}` in `{key}` {problem}")?;
+                    problems += 1;
+                }
+            }
+        }
+        let split = &config.budget_split;
+        let set = [split.question, split.code, split.attachments]
+            .iter()
+            .any(Option::is_some);
+        // Unset shares take their defaults.
+        let sum = config.budget_split().iter().sum::<u32>();
+        if set && sum != 100 {
+            writeln!(
+                writer,
+                "{}: the shares in `budget-split` add up to {sum} with the defaults of \
+                 unset ones rather than 100, and are scaled to do so",
+                location(&path, &text, "budget-split")
+            )?;
+            problems += 1;
+        }
+        layers.push((format!("from {}", path.display()), config));
+    }
+
+    writeln!(writer, "Checking the environment")?;
+    for (name, key) in ENV {
+        if std::env::var_os(name).is_some() {
+            writeln!(writer, "${name} sets `{key}`")?;
+        }
+    }
+    match env() {
+        Ok(config) => layers.push(("from the environment".to_string(), config)),
+        Err(e) => {
+            writeln!(writer, "{e}")?;
+            problems += 1;
+        }
+    }
+
+    writeln!(writer, "Checking the command line")?;
+    for (option, patterns) in [
+        ("--exclude-fn", &args.exclude_fn),
+        ("--exclude-type", &args.exclude_type),
+    ] {
+        for pattern in patterns {
+            if let Some(problem) = pattern_problem(pattern) {
+                writeln!(writer, "{option}: `{pattern}` {problem}")?;
+                problems += 1;
+            }
+        }
+    }
+    // Options override the settings, except for lists, which they extend.
+    let cli = "from the command line".to_string();
+    let extended = |source: String, added: bool| match (source.as_str(), added) {
+        (_, false) => source,
+        ("default", true) => cli.clone(),
+        (_, true) => format!("{source} and the command line"),
+    };
+
+    writeln!(writer)?;
+    writeln!(writer, "# Effective configuration")?;
+    // Paranoid mode cannot be turned off by a layer above one enabling it.
+    let enabled = layers
+        .iter()
+        .find(|(_, config)| config.paranoid == Some(true))
+        .map(|(source, _)| (true, source.as_str()));
+    let mut conflicts = Vec::new();
+    if let Some((_, enabling)) = enabled {
+        for (source, _) in layers
+            .iter()
+            .skip_while(|(source, _)| source != enabling)
+            .filter(|(_, config)| config.paranoid == Some(false))
+        {
+            conflicts.push(format!(
+                "`paranoid = false` {source} is ignored, since paranoid mode is enabled {enabling}"
+            ));
+        }
+    }
+    let (paranoid, source) = match args.paranoid {
+        true => (true, cli.as_str()),
+        false => enabled
+            .or_else(|| effective(&layers, |config| config.paranoid))
+            .unwrap_or((false, "default")),
+    };
+    writeln!(writer, "paranoid = {paranoid}  # {source}")?;
+    let (mut dirs, source) =
+        effective(&layers, |config| config.allowed_dirs.clone()).unwrap_or((Vec::new(), "default"));
+    let source = extended(source.to_string(), !args.allowed_dirs.is_empty());
+    dirs.extend(args.allowed_dirs.iter().cloned());
+    let values = dirs
+        .iter()
+        .map(|dir| toml::Value::String(dir.display().to_string()).to_string())
+        .collect::<Vec<_>>();
+    writeln!(writer, "allowed-dirs = [{}]  # {source}", values.join(", "))?;
+    let excludes: [(&str, Excludes, &[String]); 2] = [
+        ("exclude-fn", |config| &config.exclude_fn, &args.exclude_fn),
+        (
+            "exclude-type",
+            |config| &config.exclude_type,
+            &args.exclude_type,
+        ),
+    ];
+    for (key, patterns, added) in excludes {
+        let (mut patterns, source) = effective(&layers, |config| patterns(config).clone())
+            .unwrap_or((Vec::new(), "default"));
+        let source = extended(source.to_string(), !added.is_empty());
+        patterns.extend(added.iter().cloned());
+        let patterns = patterns
+            .into_iter()
+            .map(|pattern| toml::Value::String(pattern).to_string())
+            .collect::<Vec<_>>();
+        writeln!(writer, "{key} = [{}]  # {source}", patterns.join(", "))?;
+    }
+    let budget = match args.budget {
+        Some(budget) => Some((budget, cli.as_str())),
+        None => effective(&layers, |config| config.budget),
+    };
+    match budget {
+        Some((budget, source)) => writeln!(writer, "budget = {budget}  # {source}")?,
+        None => writeln!(writer, "# budget is unset")?,
+    }
+    let mut split = BudgetSplit::default();
+    for (_, config) in &layers {
+        split = BudgetSplit {
+            question: config.budget_split.question.or(split.question),
+            code: config.budget_split.code.or(split.code),
+            attachments: config.budget_split.attachments.or(split.attachments),
+        };
+    }
+    let [question, code, attachments] = Config {
+        budget_split: split,
+        ..Config::default()
+    }
+    .budget_split();
+    writeln!(
+        writer,
+        "budget-split = {{ question = {question}, code = {code}, attachments = {attachments} }}"
+    )?;
+    let mut table = "";
+    for template in Config::default().templates() {
+        let set = effective(&layers, |config| {
+            config
+                .templates()
+                .into_iter()
+                .find(|t| t.key == template.key)?
+                .value
+        });
+        let (value, source) = set.unwrap_or((template.default.to_string(), "default"));
+        let (prefix, name) = template.key.rsplit_once('.').unwrap_or(("", template.key));
+        if prefix != table {
+            table = prefix;
+            writeln!(writer, "\n[{table}]")?;
+        }
+        let selected = if template.format == Some(args.format) {
+            ", used with the selected --format"
+        } else {
+            ""
+        };
+        let value = toml::Value::String(value);
+        writeln!(writer, "{name} = {value}  # {source}{selected}")?;
+    }
+    writeln!(writer, "\n[hooks]")?;
+    let hooks: [(&str, Hook); 2] = [
+        ("pre-file", |config| &config.hooks.pre_file),
+        ("post-dump", |config| &config.hooks.post_dump),
+    ];
+    for (name, hook) in hooks {
+        match effective(&layers, |config| hook(config).clone()) {
+            Some((command, source)) => {
+                let command = toml::Value::String(command);
+                writeln!(writer, "{name} = {command}  # {source}")?;
+                if paranoid {
+                    conflicts.push(format!(
+                        "`hooks.{name}` runs a command, which paranoid mode forbids"
+                    ));
+                }
+            }
+            None => writeln!(writer, "# {name} is unset")?,
+        }
+    }
+    if paranoid {
+        if dirs.is_empty() {
+            conflicts.push(
+                "paranoid mode requires `allowed-dirs` or --allow DIR, but neither is set"
+                    .to_string(),
+            );
+        }
+        for dir in dirs.iter().filter(|dir| !dir.is_dir()) {
+            conflicts.push(format!(
+                "the allowed directory {} does not exist",
+                dir.display()
+            ));
+        }
+    }
+    if !conflicts.is_empty() {
+        writeln!(writer)?;
+        for conflict in &conflicts {
+            writeln!(writer, "Conflict: {conflict}")?;
+        }
+        problems += conflicts.len();
+    }
+
+    if problems > 0 {
+        return Err(Error::other(format!(
+            "Found {problems} problem(s) in the configuration"
+        )));
+    }
+    Ok(())
+}
+
+/// A list of patterns of a config.
+type Excludes = fn(&Config) -> &Option<Vec<String>>;
+
+/// A hook command of a config.
+type Hook = fn(&Config) -> &Option<String>;
+
+/// Returns the value of the setting given by `get` from the layer with the
+/// highest precedence setting it, with the source of that layer.
+fn effective<T>(
+    layers: &[(String, Config)],
+    get: impl Fn(&Config) -> Option<T>,
+) -> Option<(T, &str)> {
+    layers
+        .iter()
+        .rev()
+        .find_map(|(source, config)| Some((get(config)?, source.as_str())))
+}
+
+/// Returns why `pattern` of `exclude-fn` or `exclude-type` never matches, if it doesn't.
+fn pattern_problem(pattern: &str) -> Option<&'static str> {
+    if pattern.is_empty() {
+        return Some("is empty and matches nothing");
+    }
+    let valid = |c: char| c.is_alphanumeric() || matches!(c, '_' | ':' | '*');
+    if !pattern.chars().all(valid) {
+        return Some("never matches, as patterns consist of identifiers, `::` and `*`");
+    }
+    None
+}
+
+/// Returns the dotted paths of all keys in `table`, including those of tables.
+fn keys(table: &toml::Table, prefix: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    for (key, value) in table {
+        let key = format!("{prefix}{key}");
+        if let toml::Value::Table(table) = value {
+            found.extend(keys(table, &format!("{key}.")));
+        }
+        found.push(key);
+    }
+    found
+}
+
+/// Formats `path:line` of the line defining the dotted `key` in `text`, or
+/// only `path` if it is not found.
+fn location(path: &Path, text: &str, key: &str) -> String {
+    let mut table = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            table = header
+                .trim_matches(|c| c == '[' || c == ']')
+                .trim()
+                .to_string();
+            if table == key {
+                return format!("{}:{}", path.display(), i + 1);
+            }
+            continue;
+        }
+        let name = line.split('=').next().unwrap_or("").trim();
+        let full = if table.is_empty() {
+            name.to_string()
+        } else {
+            format!("{table}.{name}")
+        };
+        if full == key || (full.starts_with(key) && full[key.len()..].starts_with('.')) {
+            return format!("{}:{}", path.display(), i + 1);
+        }
+    }
+    path.display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use crate::fixture::Fixture;
+
+    fn paranoid(paranoid: Option<bool>) -> Config {
+        Config {
+            paranoid,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn paranoid_mode_cannot_be_turned_off_by_another_layer() {
+        let merged =
+            |env, project, global| paranoid(env).or(paranoid(project)).or(paranoid(global));
+        assert!(merged(Some(false), Some(true), None).paranoid());
+        assert!(merged(Some(false), None, Some(true)).paranoid());
+        assert!(merged(None, Some(false), Some(true)).paranoid());
+        assert!(merged(Some(true), Some(false), None).paranoid());
+        assert!(!merged(None, Some(false), None).paranoid());
+        assert!(!merged(None, None, None).paranoid());
+    }
+
+    /// Checks the project config `config`, returning the problems reported about `budget-split`.
+    fn split_problems(config: &str) -> Vec<String> {
+        let fixture = Fixture::new();
+        fixture.write(".cargo-gpt/config.toml", config);
+        let mut report = Vec::new();
+        let _ = check(&fixture.dir, &fixture::args(&[]), &mut report);
+        String::from_utf8(report)
+            .unwrap()
+            .lines()
+            .filter(|line| line.contains("shares in `budget-split`"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn partial_budget_splits_are_checked_with_the_defaults() {
+        assert!(split_problems("[budget-split]\nquestion = 20\ncode = 60\n").is_empty());
+        assert!(split_problems("[budget-split]\ncode = 70\n").is_empty());
+        assert!(split_problems("budget = 1000\n").is_empty());
+        let problems = split_problems("[budget-split]\ncode = 80\n");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("add up to 110"), "{problems:?}");
+        let problems =
+            split_problems("[budget-split]\nquestion = 50\ncode = 50\nattachments = 50\n");
+        assert!(problems[0].contains("add up to 150"), "{problems:?}");
+    }
+}
@@ -0,0 +1,357 @@
+use std::io::Error;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::cli::Contract;
+use crate::cli::ValidateArgs;
+
+/// The line preceding each file of an answer under the `files` contract.
+const FILE_PREFIX: &str = "FILE: ";
+
+/// Lines which `git diff` writes between the `diff --git` line of a file and
+/// its `---` line, or instead of the hunks of renamed and binary files.
+const EXTENDED_HEADERS: &[&str] = &[
+    "index ",
+    "old mode ",
+    "new mode ",
+    "deleted file mode ",
+    "new file mode ",
+    "similarity index ",
+    "dissimilarity index ",
+    "rename from ",
+    "rename to ",
+    "copy from ",
+    "copy to ",
+    "Binary files ",
+];
+
+/// Returns the response instructions appended to the prompt for `contract`.
+pub fn instructions(contract: Contract) -> String {
+    match contract {
+        Contract::Diff => "Respond only with unified diffs against the files above, as printed \
+             by `git diff`: a `--- a/PATH` and `+++ b/PATH` line per file, with paths \
+             relative to the project root, followed by hunks with `@@ -START,COUNT \
+             +START,COUNT @@` headers whose counts match their lines. Use `/dev/null` \
+             for created and deleted files. Write nothing before, between or after \
+             the diffs, and do not wrap them in a code block.\n"
+            .to_string(),
+        Contract::Files => format!(
+            "Respond only with the complete new contents of each file you change. \
+             Write a line `{FILE_PREFIX}PATH` with the path relative to the project \
+             root, followed by the contents in a fenced code block. Write nothing \
+             before, between or after the files.\n"
+        ),
+        Contract::Json => "Respond only with a JSON object of the form \
+             `{\"files\": [{\"path\": \"PATH\", \"content\": \"CONTENT\"}], \"notes\": \"NOTES\"}`, \
+             where each path is relative to the project root and each content is the \
+             complete new contents of the file, and `notes` briefly explains the change. \
+             Do not wrap the object in a code block.\n"
+            .to_string(),
+    }
+}
+
+/// Reads an answer from standard input and reports where it violates the
+/// contract it was asked to follow, with paths relative to `root`. Fails if
+/// it does.
+pub fn validate(args: &ValidateArgs, root: &Path, writer: &mut impl Write) -> Result<(), Error> {
+    let mut answer = String::new();
+    std::io::stdin().read_to_string(&mut answer)?;
+    let violations = match args.contract {
+        Contract::Diff => check_diff(&answer, root),
+        Contract::Files => check_files(&answer),
+        Contract::Json => check_json(&answer),
+    };
+    if violations.is_empty() {
+        writeln!(writer, "The answer follows the contract.")?;
+        return Ok(());
+    }
+    for violation in &violations {
+        writeln!(writer, "{violation}")?;
+    }
+    Err(Error::other(format!(
+        "The answer violates the contract in {} places",
+        violations.len()
+    )))
+}
+
+/// Checks that `answer` consists only of unified diffs whose hunk counts
+/// match their lines and which change existing files under `root`.
+fn check_diff(answer: &str, root: &Path) -> Vec<String> {
+    let mut violations = Vec::new();
+    let lines = answer.trim_end().lines().collect::<Vec<_>>();
+    if lines.is_empty() {
+        violations.push("The answer is empty".to_string());
+    }
+    // The lines still expected of the current hunk, from the old and the new file.
+    let mut old = 0;
+    let mut new = 0;
+    let mut in_file = false;
+    for (i, line) in lines.iter().enumerate() {
+        let n = i + 1;
+        if old > 0 || new > 0 {
+            match line.chars().next() {
+                Some(' ') | None => {
+                    old -= usize::from(old > 0);
+                    new -= usize::from(new > 0);
+                }
+                Some('-') => old -= usize::from(old > 0),
+                Some('+') => new -= usize::from(new > 0),
+                Some('\\') => {}
+                _ => {
+                    violations.push(format!("Line {n}: the hunk above ends early"));
+                    old = 0;
+                    new = 0;
+                }
+            }
+            if old > 0 || new > 0 {
+                continue;
+            }
+            if matches!(line.chars().next(), Some(' ' | '-' | '+') | None) {
+                continue;
+            }
+        }
+        if line.starts_with("diff ") || line.starts_with("\\") {
+            continue;
+        }
+        if EXTENDED_HEADERS
+            .iter()
+            .any(|header| line.starts_with(header))
+        {
+            let source = line
+                .strip_prefix("rename from ")
+                .or(line.strip_prefix("copy from "));
+            if let Some(path) = source.filter(|path| !root.join(path).is_file()) {
+                violations.push(format!("Line {n}: `{path}` is not a file of the project"));
+            }
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("--- ") {
+            in_file = true;
+            if let Some(path) = strip_side(path, "a/") {
+                if !root.join(path).is_file() {
+                    violations.push(format!("Line {n}: `{path}` is not a file of the project"));
+                }
+            }
+        } else if line.starts_with("+++ ") {
+            if !in_file {
+                violations.push(format!("Line {n}: `+++` without a preceding `---` line"));
+            }
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            if !in_file {
+                violations.push(format!("Line {n}: a hunk outside of a file"));
+            }
+            match hunk_counts(header) {
+                Some((o, c)) => (old, new) = (o, c),
+                None => violations.push(format!("Line {n}: malformed hunk header")),
+            }
+        } else {
+            violations.push(format!("Line {n}: not part of a unified diff"));
+        }
+    }
+    if old > 0 || new > 0 {
+        violations.push("The last hunk is shorter than its header says".to_string());
+    }
+    violations
+}
+
+/// Returns the path of a `---` line without its `a/` prefix and timestamp,
+/// or `None` for `/dev/null`.
+fn strip_side<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let path = path.split('\t').next()?.trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path))
+}
+
+/// Parses the line counts of the old and new file from a hunk header such as
+/// `-12,3 +12,4 @@`. An omitted count is one.
+fn hunk_counts(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let count = |range: Option<&str>, sign: char| -> Option<usize> {
+        let range = range?.strip_prefix(sign)?;
+        match range.split_once(',') {
+            Some((start, count)) => {
+                start.parse::<usize>().ok()?;
+                count.parse().ok()
+            }
+            None => range.parse::<usize>().ok().map(|_| 1),
+        }
+    };
+    let old = count(ranges.next(), '-')?;
+    let new = count(ranges.next(), '+')?;
+    (ranges.next() == Some("@@")).then_some((old, new))
+}
+
+/// Checks that `answer` consists only of `FILE:` lines, each followed by a
+/// fenced code block.
+fn check_files(answer: &str) -> Vec<String> {
+    let mut violations = Vec::new();
+    let mut files = 0;
+    // The fence of the open code block and whether a `FILE:` line awaits its block.
+    let mut fence: Option<&str> = None;
+    let mut pending = false;
+    for (i, line) in answer.lines().enumerate() {
+        let n = i + 1;
+        if let Some(open) = fence {
+            if line.trim_end() == open {
+                fence = None;
+            }
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(path) = line.strip_prefix(FILE_PREFIX) {
+            if pending {
+                violations.push(format!("Line {n}: the file above has no code block"));
+            }
+            if path.trim().is_empty() {
+                violations.push(format!("Line {n}: `{FILE_PREFIX}` without a path"));
+            }
+            pending = true;
+        } else if line.starts_with("```") || line.starts_with("~~~") {
+            if !pending {
+                violations.push(format!(
+                    "Line {n}: a code block without a `{FILE_PREFIX}` line"
+                ));
+            }
+            let width = line.len() - line.trim_start_matches(['`', '~']).len();
+            fence = Some(&line[..width]);
+            pending = false;
+            files += 1;
+        } else {
+            violations.push(format!("Line {n}: text outside of the files"));
+        }
+    }
+    if pending {
+        violations.push("The last file has no code block".to_string());
+    }
+    if fence.is_some() {
+        violations.push("The last code block is not closed".to_string());
+    }
+    if files == 0 && violations.is_empty() {
+        violations.push("The answer contains no files".to_string());
+    }
+    violations
+}
+
+/// Checks that `answer` is a JSON object with a `files` array of objects
+/// with string `path` and `content` fields.
+fn check_json(answer: &str) -> Vec<String> {
+    let value = match serde_json::from_str::<Value>(answer.trim()) {
+        Ok(value) => value,
+        Err(e) => return vec![format!("The answer is not valid JSON: {e}")],
+    };
+    let Some(files) = value.get("files").and_then(Value::as_array) else {
+        return vec!["The answer has no `files` array".to_string()];
+    };
+    let mut violations = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        for field in ["path", "content"] {
+            if !file.get(field).is_some_and(Value::is_string) {
+                violations.push(format!("`files[{i}]` has no string `{field}`"));
+            }
+        }
+    }
+    if value.get("notes").is_some_and(|notes| !notes.is_string()) {
+        violations.push("`notes` is not a string".to_string());
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    /// A project with the files which the diffs below change.
+    fn project() -> Fixture {
+        let fixture = Fixture::new();
+        for path in ["src/main.rs", "src/tokens.rs", "src/stats.rs"] {
+            fixture.write(path, "use std::io::Error;\n");
+        }
+        fixture
+    }
+
+    #[test]
+    fn diffs_with_git_headers_are_valid() {
+        let answer = "\
+diff --git a/src/main.rs b/src/main.rs
+index 0123abc..4567def 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,2 +1,2 @@
+-use std::io::BufWriter;
++use std::io::BufReader;
+ use std::io::Error;
+diff --git a/src/new.rs b/src/new.rs
+new file mode 100644
+index 0000000..89abcde
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1 @@
++fn new() {}
+diff --git a/src/tokens.rs b/src/count.rs
+similarity index 100%
+rename from src/tokens.rs
+rename to src/count.rs
+diff --git a/src/stats.rs b/src/stats.rs
+deleted file mode 100644
+--- a/src/stats.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-use std::io::Error;
+";
+        let project = project();
+        assert_eq!(check_diff(answer, &project.dir), Vec::<String>::new());
+    }
+
+    #[test]
+    fn diffs_with_wrong_counts_or_paths_are_invalid() {
+        let answer = "\
+Here is the fix:
+--- a/src/missing.rs
++++ b/src/missing.rs
+@@ -1,3 +1,3 @@
+-a
++b
+";
+        let project = project();
+        assert_eq!(
+            check_diff(answer, &project.dir),
+            [
+                "Line 1: not part of a unified diff",
+                "Line 2: `src/missing.rs` is not a file of the project",
+                "The last hunk is shorter than its header says",
+            ]
+        );
+    }
+
+    #[test]
+    fn files_need_a_code_block_each() {
+        assert!(check_files("FILE: src/a.rs\n```rust\nfn a() {}\n```\n").is_empty());
+        assert_eq!(
+            check_files("FILE: src/a.rs\nFILE: src/b.rs\n```\n```\nDone.\n"),
+            [
+                "Line 2: the file above has no code block",
+                "Line 5: text outside of the files",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_needs_paths_and_contents() {
+        assert!(check_json(r#"{"files": [{"path": "a", "content": ""}], "notes": ""}"#).is_empty());
+        assert_eq!(
+            check_json(r#"{"files": [{"path": "a"}], "notes": 1}"#),
+            [
+                "`files[0]` has no string `content`",
+                "`notes` is not a string"
+            ]
+        );
+    }
+}
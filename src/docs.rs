@@ -0,0 +1,95 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use ignore::WalkBuilder;
+
+/// Directories which conventionally hold design documents, relative to the
+/// project root: documentation, mdBook sources and architecture decision records.
+const DOC_DIRS: &[&str] = &[
+    "docs",
+    "doc",
+    "book/src",
+    "adr",
+    "adrs",
+    "decisions",
+    "rfcs",
+];
+
+/// Returns the markdown files in the documentation directories of `root` and
+/// directly in `root`, such as `ARCHITECTURE.md`.
+pub fn doc_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in DOC_DIRS.iter().map(|dir| root.join(dir)) {
+        if dir.is_dir() {
+            files.extend(
+                WalkBuilder::new(&dir)
+                    .build()
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.into_path())
+                    .filter(|path| path.is_file() && is_markdown(path)),
+            );
+        }
+    }
+    if let Ok(entries) = std::fs::read_dir(root) {
+        files.extend(
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && is_markdown(path)),
+        );
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
+
+/// Trims markdown to its headings and the first paragraph below each of them.
+/// Code blocks are dropped.
+pub fn outline(markdown: &str) -> String {
+    let mut outline = String::new();
+    let mut fence: Option<&str> = None;
+    // Whether the first paragraph below the last heading was not yet kept,
+    // and whether it is being kept.
+    let mut want_paragraph = true;
+    let mut in_paragraph = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            in_paragraph = false;
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            if !outline.is_empty() {
+                outline.push('\n');
+            }
+            outline.push_str(line);
+            outline.push('\n');
+            want_paragraph = true;
+            in_paragraph = false;
+        } else if trimmed.is_empty() {
+            if in_paragraph {
+                in_paragraph = false;
+                want_paragraph = false;
+            }
+        } else if want_paragraph || in_paragraph {
+            if !in_paragraph && !outline.is_empty() && !outline.ends_with("\n\n") {
+                outline.push('\n');
+            }
+            outline.push_str(line);
+            outline.push('\n');
+            in_paragraph = true;
+        }
+    }
+    outline
+}
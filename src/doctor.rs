@@ -0,0 +1,135 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::config;
+
+/// Tools which copy standard input to the clipboard, with the arguments they
+/// need and the platforms they are for.
+pub const CLIPBOARD_TOOLS: &[(&str, &[&str], &str)] = &[
+    ("pbcopy", &[], "macOS"),
+    ("wl-copy", &[], "Wayland"),
+    ("xclip", &["-selection", "clipboard"], "X11"),
+    ("xsel", &["--clipboard", "--input"], "X11"),
+    ("clip", &[], "Windows"),
+];
+
+/// Subcommands which need a cargo plugin, with the crate to install it from.
+const PLUGINS: &[(&str, &str, &str)] = &[
+    ("audit", "cargo-audit", "cargo gpt audit"),
+    ("bloat", "cargo-bloat", "cargo gpt bloat"),
+];
+
+/// Checks the tools and files cargo-gpt depends on and prints how to fix the
+/// problems found. Fails if a required tool is missing.
+pub fn run(dir: &Path, writer: &mut impl Write) -> Result<(), Error> {
+    let mut missing = 0;
+    for tool in ["cargo", "rustc"] {
+        match version(Command::new(tool).arg("--version")) {
+            Some(version) => writeln!(writer, "[ok] {version}")?,
+            None => {
+                writeln!(
+                    writer,
+                    "[error] `{tool}` was not found. Install Rust from https://rustup.rs."
+                )?;
+                missing += 1;
+            }
+        }
+    }
+    match version(Command::new("git").arg("--version")) {
+        Some(version) => writeln!(writer, "[ok] {version}")?,
+        None => writeln!(
+            writer,
+            "[warn] `git` was not found, which `remote` and `api-diff` need."
+        )?,
+    }
+    for (subcommand, krate, user) in PLUGINS {
+        match version(Command::new("cargo").args([subcommand, "--version"])) {
+            Some(version) => writeln!(writer, "[ok] {version}")?,
+            None => writeln!(
+                writer,
+                "[warn] `cargo {subcommand}` was not found, which `{user}` needs. \
+                 Install it with `cargo install {krate}`."
+            )?,
+        }
+    }
+
+    for (scope, path) in config::files(dir) {
+        match path.filter(|path| path.exists()) {
+            None => writeln!(writer, "[ok] No {scope} config file.")?,
+            Some(path) => match config::read(&path) {
+                Ok(_) => writeln!(writer, "[ok] {scope} config file {}", path.display())?,
+                Err(e) => writeln!(
+                    writer,
+                    "[warn] The {scope} config file {} cannot be used: {e}",
+                    path.display()
+                )?,
+            },
+        }
+    }
+
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR"));
+    let program = editor.as_deref().unwrap_or("vi");
+    let program = program.split_whitespace().next().unwrap_or(program);
+    match find(program) {
+        Some(path) => writeln!(writer, "[ok] `--edit` uses {}", path.display())?,
+        None if editor.is_err() => writeln!(
+            writer,
+            "[warn] Neither `$VISUAL` nor `$EDITOR` is set and `vi` was not found, \
+             which `--edit` needs. Set `EDITOR` to your editor."
+        )?,
+        None => writeln!(
+            writer,
+            "[warn] The editor `{program}` was not found, which `--edit` needs. \
+             Set `EDITOR` to an editor on your `PATH`."
+        )?,
+    }
+
+    let clipboard = CLIPBOARD_TOOLS
+        .iter()
+        .filter(|(tool, _, _)| find(tool).is_some())
+        .map(|(tool, _, platform)| format!("`{tool}` ({platform})"))
+        .collect::<Vec<_>>();
+    if clipboard.is_empty() {
+        writeln!(
+            writer,
+            "[warn] No clipboard tool was found to pipe the dump into. \
+             Install `wl-copy` (Wayland), `xclip` (X11) or write the dump to a file."
+        )?;
+    } else {
+        writeln!(
+            writer,
+            "[ok] Pipe the dump into {} to copy it",
+            clipboard.join(" or ")
+        )?;
+    }
+
+    if missing > 0 {
+        return Err(Error::other("Required tools are missing"));
+    }
+    Ok(())
+}
+
+/// Returns the first line printed by a `--version` command, if it succeeds.
+pub fn version(command: &mut Command) -> Option<String> {
+    let output = command.output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next()?.trim();
+    (output.status.success() && !line.is_empty()).then(|| line.to_string())
+}
+
+/// Returns the path of the executable `program` on the `PATH`.
+pub fn find(program: &str) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let path = dir.join(program);
+        let exe = path.with_extension(std::env::consts::EXE_EXTENSION);
+        [path, exe].into_iter().find(|path| path.is_file())
+    })
+}
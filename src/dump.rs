@@ -0,0 +1,573 @@
+use std::collections::HashSet;
+use std::io::Error;
+use std::io::Write;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use ignore::WalkBuilder;
+use ignore::WalkState;
+
+use crate::annotate;
+use crate::budget::Part;
+use crate::cli::Args;
+use crate::cli::Inclusion;
+use crate::config::Config;
+use crate::docs;
+use crate::elide;
+use crate::examples;
+use crate::expand;
+use crate::handlers;
+use crate::hooks;
+use crate::manifest::Tracked;
+use crate::progress;
+use crate::project::Package;
+use crate::project::Project;
+use crate::summary;
+use crate::tokens;
+use crate::traits;
+
+/// Replaces the content of files which were already sent in the session.
+const UNCHANGED: &str = "(unchanged since the previous message)\n";
+
+/// Directory names which conventionally hold third-party code.
+const VENDOR_DIRS: &[&str] = &["vendor", "third_party", "third-party"];
+
+pub fn write_project(
+    project: &Project,
+    args: &Args,
+    config: &Config,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let (files, skipped) = walk_project(project, args);
+    write_header(project, &skipped, writer)?;
+    for file in files {
+        read_file(&file, project, args, config, writer)?;
+    }
+    if args.foreign_traits {
+        traits::write_foreign_traits(project, args, config, writer)?;
+    }
+    if !args.examples.is_empty() {
+        examples::write_examples(project, args, config, writer)?;
+    }
+    Ok(())
+}
+
+/// Writes a summary of the project which is not obvious from the sources
+/// alone, including the vendored directories which were `skipped`.
+pub fn write_header(
+    project: &Project,
+    skipped: &[PathBuf],
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let mut header = String::new();
+    for package in &project.packages {
+        if let Some(edition) = &package.edition {
+            header.push_str(&format!(
+                "// Crate `{}` uses edition {edition}\n",
+                package.name
+            ));
+        }
+        write_features(package, &mut header);
+    }
+    if !skipped.is_empty() {
+        let dirs = skipped
+            .iter()
+            .map(|dir| format!("{}/", project.header_path(dir)))
+            .collect::<Vec<_>>();
+        header.push_str(&format!("// Skipped vendored code: {}\n", dirs.join(", ")));
+    }
+    // Names of crates and features may be identifying.
+    let header = writer.anonymized(&header);
+    writer.write_all(header.as_bytes())
+}
+
+/// Writes the features of `package`, marking those enabled by default.
+fn write_features(package: &Package, header: &mut String) {
+    if package.features.is_empty() {
+        return;
+    }
+    let mut defaults = HashSet::new();
+    let mut stack = vec!["default"];
+    while let Some(feature) = stack.pop() {
+        for enabled in package.features.get(feature).into_iter().flatten() {
+            if package.features.contains_key(enabled) && defaults.insert(enabled.as_str()) {
+                stack.push(enabled.as_str());
+            }
+        }
+    }
+    header.push_str(&format!("// Features of `{}`:\n", package.name));
+    for (feature, enables) in &package.features {
+        if feature == "default" {
+            continue;
+        }
+        let default = if defaults.contains(feature.as_str()) {
+            " (default)"
+        } else {
+            ""
+        };
+        header.push_str(&format!("//   {feature}{default} = {enables:?}\n"));
+    }
+}
+
+/// Returns the files of all packages in the project, without duplicates.
+pub fn project_files(project: &Project, args: &Args) -> Vec<PathBuf> {
+    walk_project(project, args).0
+}
+
+/// Returns the files of all packages in the project, without duplicates, and
+/// the vendored directories skipped while looking for them.
+pub fn walk_project(project: &Project, args: &Args) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for package in &project.packages {
+        let mut walked = Vec::new();
+        for dir in &package.source_dirs {
+            let (found, vendored) = walk(dir, &project.target_dir, args);
+            walked.extend(found);
+            skipped.extend(vendored);
+        }
+        let generated = package.out_dir.iter().flat_map(|dir| generated_files(dir));
+        for file in package
+            .manifest
+            .iter()
+            .chain(&package.readme)
+            .cloned()
+            .chain(walked)
+            .chain(package.source_files.iter().cloned())
+            .chain(generated)
+        {
+            if seen.insert(file.clone()) {
+                files.push(file);
+            }
+        }
+    }
+    if args.data {
+        let lockfile = project.root.join("Cargo.lock");
+        if lockfile.is_file() && seen.insert(lockfile.clone()) {
+            files.push(lockfile);
+        }
+    }
+    if args.toml {
+        for file in &project.manifests {
+            if file.is_file() && seen.insert(file.clone()) {
+                files.push(file.clone());
+            }
+        }
+    }
+    if args.submodules.is_some() {
+        for dir in &project.submodules {
+            for file in source_files(dir, &project.target_dir, args) {
+                if seen.insert(file.clone()) {
+                    files.push(file);
+                }
+            }
+        }
+    }
+    if args.docs {
+        for file in docs::doc_files(&project.root) {
+            if seen.insert(file.clone()) {
+                files.push(file);
+            }
+        }
+    }
+    files.retain(|file| !project.excluded.contains(file));
+    // Packages and submodules of the dump are walked on their own.
+    skipped.retain(|dir| {
+        let included = args.submodules.is_some() && project.submodules.contains(dir);
+        !included && !project.packages.iter().any(|p| p.source_dirs.contains(dir))
+    });
+    skipped.sort();
+    skipped.dedup();
+    (files, skipped)
+}
+
+/// Returns the files under `path` that belong in a dump, sorted so that the
+/// order does not depend on how the parallel walk was scheduled.
+pub fn source_files(path: &Path, target_dir: &Path, args: &Args) -> Vec<PathBuf> {
+    walk(path, target_dir, args).0
+}
+
+/// Returns the files under `path` that belong in a dump, sorted, and the
+/// vendored directories skipped unless `--include-vendored` is given.
+fn walk(path: &Path, target_dir: &Path, args: &Args) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let target_dir = target_dir.to_path_buf();
+    let include_vendored = args.include_vendored.is_some();
+    let data = args.data;
+    let skipped = Arc::new(Mutex::new(Vec::new()));
+    let pruned = Arc::clone(&skipped);
+    // Excluded directories are pruned here, before the walk descends into them.
+    let walk = WalkBuilder::new(path)
+        .filter_entry(move |e| {
+            let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+            let included = e.path() != target_dir
+                && e.file_name()
+                    .to_str()
+                    .map(|s| !s.starts_with('.'))
+                    .unwrap_or(false);
+            if included && !include_vendored && e.depth() > 0 && is_dir && is_vendored(e.path()) {
+                pruned.lock().unwrap().push(e.path().to_path_buf());
+                return false;
+            }
+            included
+        })
+        .build_parallel();
+
+    let report = progress::walking(path);
+    let files = Mutex::new(Vec::new());
+    walk.run(|| {
+        let files = &files;
+        Box::new(move |entry| {
+            if let Ok(entry) = entry {
+                if entry.file_type().is_some_and(|t| t.is_file())
+                    && is_source_file(entry.path(), data)
+                {
+                    files.lock().unwrap().push(entry.into_path());
+                    // Reported as they are found, since the walk is the slow part.
+                    if report {
+                        progress::walked(1);
+                    }
+                }
+            }
+            WalkState::Continue
+        })
+    });
+    let mut files = files.into_inner().unwrap();
+    files.sort();
+    let skipped = std::mem::take(&mut *skipped.lock().unwrap());
+    (files, skipped)
+}
+
+fn is_source_file(path: &Path, data: bool) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "rs" || ext == "md")
+        || path.file_name().is_some_and(|name| name == "Cargo.toml")
+        || (data && handlers::find(path).is_some())
+}
+
+/// Returns true if the directory `path` holds third-party code: a conventional
+/// vendor directory, a git submodule, or a checkout of another package.
+fn is_vendored(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| VENDOR_DIRS.contains(&name))
+        || path.join(".git").exists()
+        || path.join("Cargo.toml").is_file()
+}
+
+/// Returns true if `path` is inside a directory holding third-party code
+/// within the source directory of its package.
+fn in_vendored(path: &Path, project: &Project) -> bool {
+    let Some(root) = project
+        .packages
+        .iter()
+        .flat_map(|package| &package.source_dirs)
+        .filter(|dir| path.starts_with(dir))
+        .max_by_key(|dir| dir.components().count())
+    else {
+        return false;
+    };
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| dir != root)
+        .any(is_vendored)
+}
+
+/// Returns the Rust files a build script generated into `out_dir`, which is
+/// usually ignored by version control.
+fn generated_files(out_dir: &Path) -> Vec<PathBuf> {
+    let mut files = WalkBuilder::new(out_dir)
+        .standard_filters(false)
+        .build()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "rs"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+pub fn read_file(
+    path: &Path,
+    project: &Project,
+    args: &Args,
+    config: &Config,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let header = project.header_path(path);
+    let bytes = std::fs::read(path)?;
+    let mut content = handlers::render(path, &bytes)
+        .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned());
+    if args.normalize_newlines {
+        content = content.replace("\r\n", "\n");
+    }
+    let is_rust = path.extension().is_some_and(|ext| ext == "rs");
+    if is_rust && args.summarize.iter().any(|p| elide::glob(p, &header)) {
+        content = summary::cached(project, path, &bytes)?;
+    }
+    if args.docs_outline && docs::is_markdown(path) {
+        content = docs::outline(&content);
+    }
+    if is_rust && project.elided.iter().any(|(file, _)| file == path) {
+        let elided = |item: &elide::Item| {
+            project
+                .elided
+                .iter()
+                .any(|(file, name)| file == path && *name == item.path())
+        };
+        content = elide::elide_bodies(&content, config.elision_marker(), |item| !elided(item));
+    }
+    if is_rust {
+        let exclude_fn = args
+            .exclude_fn
+            .iter()
+            .chain(config.exclude_fn())
+            .collect::<Vec<_>>();
+        if !exclude_fn.is_empty() {
+            content = elide::elide_bodies(&content, config.elision_marker(), |item| {
+                !exclude_fn.iter().any(|pattern| item.matches(pattern))
+            });
+        }
+        let exclude_type = args
+            .exclude_type
+            .iter()
+            .chain(config.exclude_type())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if !exclude_type.is_empty() {
+            content = elide::elide_types(&content, config.elision_marker(), &exclude_type);
+        }
+    }
+    let in_submodule = project.submodules.iter().any(|dir| path.starts_with(dir));
+    let outline = (in_submodule && args.submodules == Some(Inclusion::Outline))
+        || (args.include_vendored == Some(Inclusion::Outline) && in_vendored(path, project));
+    if outline {
+        if is_rust {
+            content = elide::elide_bodies(&content, config.elision_marker(), |_| false);
+        } else if docs::is_markdown(path) && !args.docs_outline {
+            content = docs::outline(&content);
+        }
+    }
+    let package = project.package_of(path);
+    if let Some(package) = package {
+        if args.annotate_deps && is_rust {
+            content = annotate::annotate_deps(&content, &package.dependencies);
+        }
+        if !package.expansions.is_empty() && is_rust {
+            content = expand::insert(&content, &package.expansions);
+        }
+    }
+    if let Some(hook) = &config.hooks.pre_file {
+        let source = path.display().to_string();
+        let vars = [
+            ("CARGO_GPT_PATH", header.as_str()),
+            ("CARGO_GPT_SOURCE", &source),
+        ];
+        content = hooks::pipe(hook, &content, &vars)?;
+    }
+    let header = writer.anonymized(&header);
+    let content = writer.anonymized(&content);
+    let part = if is_rust {
+        Part::Code
+    } else {
+        Part::Attachments
+    };
+    let content = writer.fit(part, content, is_rust, config.elision_marker());
+    writer.section(&header, path, &bytes, &content, |writer, unchanged| {
+        let template = config.file_header(args.format);
+        let content = if unchanged {
+            writer.refund(part, &content);
+            UNCHANGED.to_string()
+        } else if is_rust && args.answer_anchors {
+            insert_anchors(&content, &String::from_utf8_lossy(&bytes), &header, writer)
+        } else {
+            content.clone()
+        };
+        progress::file(&header, tokens::estimate(&content));
+        args.format.write_file(template, &header, &content, writer)
+    })
+}
+
+/// Inserts an anchor comment such as `// [A17]` above every item of
+/// `content`, recording the line of the item in the original `source`.
+fn insert_anchors(
+    content: &str,
+    source: &str,
+    header: &str,
+    writer: &mut Tracked<impl Write>,
+) -> String {
+    let lines = elide::items(source)
+        .into_iter()
+        .map(|item| {
+            let line = source[..item.signature.start].matches('\n').count() + 1;
+            (item.path(), line)
+        })
+        .collect::<Vec<_>>();
+    let mut anchored = String::with_capacity(content.len());
+    let mut copied = 0;
+    let mut anchored_line = None;
+    for item in elide::items(content) {
+        let start = content[..item.range.start].rfind('\n').map_or(0, |i| i + 1);
+        // Items which do not start their line, or share it with another item.
+        if anchored_line == Some(start) || !content[start..item.range.start].trim().is_empty() {
+            continue;
+        }
+        let rest = &content[start..];
+        let indent = &rest[..rest.len() - rest.trim_start_matches([' ', '\t']).len()];
+        let path = item.path();
+        let line = lines
+            .iter()
+            .find(|(p, _)| *p == path)
+            .map_or(0, |(_, line)| *line);
+        let Some(id) = writer.anchor(header, path, line) else {
+            break;
+        };
+        anchored.push_str(&content[copied..start]);
+        anchored.push_str(&format!("{indent}// [{id}]\n"));
+        copied = start;
+        anchored_line = Some(start);
+    }
+    anchored.push_str(&content[copied..]);
+    anchored
+}
+
+/// Joins the components of `path` with `/` on all platforms, so that dumps
+/// do not depend on the machine.
+pub fn slashed(path: &Path) -> String {
+    let mut slashed = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => {
+                slashed.push_str(&prefix.as_os_str().to_string_lossy().replace('\\', "/"));
+            }
+            Component::RootDir => slashed.push('/'),
+            component => {
+                if !slashed.is_empty() && !slashed.ends_with('/') {
+                    slashed.push('/');
+                }
+                slashed.push_str(&component.as_os_str().to_string_lossy());
+            }
+        }
+    }
+    slashed
+}
+
+/// Canonicalizes `path` without the `\\?\` prefix Windows adds to it, so that
+/// it can be compared with the paths cargo reports. Long paths are still
+/// handled, since the standard library adds the prefix where it is needed.
+pub fn canonicalize(path: &Path) -> Result<PathBuf, Error> {
+    let path = path.canonicalize()?;
+    match path.to_str().and_then(|p| p.strip_prefix(r"\\?\")) {
+        Some(rest) if !rest.starts_with(r"UNC\") => Ok(PathBuf::from(rest)),
+        _ => Ok(path),
+    }
+}
+
+/// Makes `path` relative to `root`, going up through `..` for paths outside of it.
+pub fn relative(path: &Path, root: &Path) -> PathBuf {
+    let mut base = root;
+    let mut up = PathBuf::new();
+    loop {
+        if let Ok(rest) = path.strip_prefix(base) {
+            return up.join(rest);
+        }
+        match base.parent() {
+            Some(parent) => {
+                base = parent;
+                up.push("..");
+            }
+            None => return path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anonymize::Anonymizer;
+    use crate::fixture;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn vendored_code_is_skipped_or_outlined() {
+        let fixture = Fixture::new();
+        let lib = fixture.write("src/lib.rs", "pub fn a() {}\n");
+        let vendored = fixture.write("vendor/dep/src/lib.rs", "pub fn dep() {\n    1;\n}\n");
+        let project = Project::quick(&fixture.dir);
+
+        let (files, skipped) = walk_project(&project, &fixture::args(&[]));
+        assert_eq!(files, std::slice::from_ref(&lib));
+        assert_eq!(skipped, [fixture.dir.join("vendor")]);
+        let mut header = Tracked::new(Vec::new());
+        write_header(&project, &skipped, &mut header).unwrap();
+        assert_eq!(
+            String::from_utf8(header.into_inner()).unwrap(),
+            "// Skipped vendored code: vendor/\n"
+        );
+
+        let args = fixture::args(&["--include-vendored", "outline"]);
+        let (files, skipped) = walk_project(&project, &args);
+        assert_eq!(files, [lib, vendored.clone()]);
+        assert!(skipped.is_empty());
+        let mut writer = Tracked::new(Vec::new());
+        read_file(&vendored, &project, &args, &Config::default(), &mut writer).unwrap();
+        let dump = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(dump.contains("pub fn dep() { /* ... */ }"));
+    }
+
+    #[test]
+    fn anonymized_dumps_contain_no_package_name() {
+        let fixture = Fixture::new();
+        fixture.write("src/lib.rs", "use acme_core::Engine;\n");
+        let mut project = Project::quick(&fixture.dir);
+        let package = &mut project.packages[0];
+        package.name = "acme-core".to_string();
+        package.edition = Some("2021".to_string());
+        package.features = [("default", vec!["fast"]), ("fast", vec![])]
+            .into_iter()
+            .map(|(feature, enables)| {
+                let enables = enables.into_iter().map(str::to_string).collect();
+                (feature.to_string(), enables)
+            })
+            .collect();
+        let names = ["acme-core".to_string()];
+        let path = fixture.dir.join("anonymize.json");
+        let anonymizer = Anonymizer::load(path, &[fixture.dir.as_path()], &names).unwrap();
+        let mut writer = Tracked::new(Vec::new());
+        writer.anonymize(anonymizer);
+        let args = fixture::args(&["--anonymize", "--anonymize-names", "acme-core"]);
+        write_project(&project, &args, &Config::default(), &mut writer).unwrap();
+        let dump = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(dump.contains("uses edition 2021"), "{dump}");
+        assert!(dump.contains("(default)"), "{dump}");
+        assert!(!dump.to_lowercase().contains("acme"), "{dump}");
+    }
+
+    #[test]
+    fn slashed_keeps_the_root() {
+        assert_eq!(slashed(Path::new("src/lib.rs")), "src/lib.rs");
+        assert_eq!(
+            slashed(Path::new("../shared/src/lib.rs")),
+            "../shared/src/lib.rs"
+        );
+        assert_eq!(slashed(Path::new("/tmp/out.txt")), "/tmp/out.txt");
+        assert_eq!(slashed(Path::new("")), "");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn slashed_replaces_backslashes() {
+        assert_eq!(slashed(Path::new(r"src\lib.rs")), "src/lib.rs");
+        assert_eq!(
+            slashed(Path::new(r"C:\Users\me\out.txt")),
+            "C:/Users/me/out.txt"
+        );
+        assert_eq!(
+            slashed(Path::new(r"\\server\share\out.txt")),
+            "//server/share/out.txt"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use crate::temp;
+
+/// Everything from this line on is not part of the question, like below the
+/// scissors line of `git commit --verbose`. Markdown headings in the question
+/// start with `#`, so comment lines cannot be used.
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// Opens the user's editor on a template listing `files`, and returns the
+/// question written into it above the scissors line.
+pub fn compose(files: &[String]) -> Result<String, Error> {
+    let mut template = format!(
+        "\n\n{SCISSORS}\n\
+         Write your question above. Do not modify or remove the line above,\n\
+         everything below it is ignored. An empty question aborts the dump.\n\
+         \n\
+         Files included in the dump:\n"
+    );
+    for file in files {
+        template.push_str(&format!("  {file}\n"));
+    }
+    let (path, mut file) = temp::create_file("cargo-gpt", ".md")?;
+    let written = file.write_all(template.as_bytes());
+    drop(file);
+    let edited = written
+        .and_then(|()| open(&path))
+        .and_then(|()| std::fs::read_to_string(&path));
+    let _ = std::fs::remove_file(&path);
+
+    let edited = edited?;
+    let question = strip(&edited);
+    if question.is_empty() {
+        return Err(Error::other("Aborting due to an empty question"));
+    }
+    Ok(format!("{question}\n\n"))
+}
+
+/// Returns the question in the `edited` buffer, without the scissors line and
+/// everything below it.
+fn strip(edited: &str) -> &str {
+    let end = edited
+        .lines()
+        .position(|line| line.trim_end() == SCISSORS)
+        .map_or(edited.len(), |n| {
+            edited.split_inclusive('\n').take(n).map(str::len).sum()
+        });
+    edited[..end].trim()
+}
+
+/// Runs `$VISUAL` or `$EDITOR` on `path` and waits for it to exit. The
+/// variables may contain arguments, such as `code --wait`.
+fn open(path: &Path) -> Result<(), Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| Error::other("`$EDITOR` is empty"))?;
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|e| Error::other(format!("Failed to run `{editor}`: {e}")))?;
+    if !status.success() {
+        return Err(Error::other(format!("`{editor}` failed")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_keeps_headings_above_the_scissors() {
+        let edited = format!("# Context\n\nWhy?\n\n{SCISSORS}\nFiles:\n  src/a.rs\n");
+        assert_eq!(strip(&edited), "# Context\n\nWhy?");
+        assert_eq!(strip("## Only a question\n"), "## Only a question");
+        assert_eq!(strip(&format!("\n{SCISSORS}\n")), "");
+    }
+}
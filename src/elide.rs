@@ -0,0 +1,806 @@
+//! A lightweight scanner for the items of Rust source files, used to elide
+//! function bodies and extract individual items without a full parser.
+
+use std::ops::Range;
+
+use crate::tokens;
+
+/// The default template replacing a function body.
+pub const MARKER: &str = "{ /* ... */ }";
+
+/// Qualifiers which may precede the keyword of an item.
+const QUALIFIERS: &[&str] = &["async", "unsafe", "const", "extern", "default"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ItemKind {
+    Fn,
+    Struct,
+    Enum,
+    Union,
+    Trait,
+}
+
+#[derive(Debug)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub name: String,
+    /// Names of the enclosing modules, traits and implemented types, outermost first.
+    pub scope: Vec<String>,
+    /// The whole item, including the attributes and comments directly above it.
+    pub range: Range<usize>,
+    /// The item from its qualifiers up to the body of a function, or to the
+    /// end of other items.
+    pub signature: Range<usize>,
+    /// The braces of a function body.
+    pub body: Option<Range<usize>>,
+    /// Whether the item is declared `pub` without restrictions.
+    pub public: bool,
+}
+
+impl Item {
+    /// Returns the name of the item qualified by its scope, such as `tests::it_works`.
+    pub fn path(&self) -> String {
+        let mut path = self.scope.join("::");
+        if !path.is_empty() {
+            path.push_str("::");
+        }
+        path.push_str(&self.name);
+        path
+    }
+
+    /// Returns true if `pattern` matches the name or path of the item, where
+    /// `*` in the pattern matches any sequence of characters.
+    pub fn matches(&self, pattern: &str) -> bool {
+        glob(pattern, &self.name) || glob(pattern, &self.path())
+    }
+}
+
+/// Replaces the bodies of all functions not selected by `keep` with a
+/// [`marker`] rendered from `template`. Functions nested inside an elided body
+/// disappear along with it.
+pub fn elide_bodies(source: &str, template: &str, keep: impl Fn(&Item) -> bool) -> String {
+    let mut elided = String::with_capacity(source.len());
+    let mut copied = 0;
+    for item in items(source) {
+        let Some(body) = &item.body else { continue };
+        if body.start < copied || keep(&item) {
+            continue;
+        }
+        elided.push_str(&source[copied..body.start]);
+        elided.push_str(&marker(template, &source[body.clone()]));
+        copied = body.end;
+    }
+    elided.push_str(&source[copied..]);
+    elided
+}
+
+/// Renders the marker replacing `body`, substituting `{lines}` and `{tokens}`
+/// in the template by the size of the body.
+pub fn marker(template: &str, body: &str) -> String {
+    template
+        .replace("{lines}", &body.lines().count().to_string())
+        .replace("{tokens}", &tokens::estimate(body).to_string())
+}
+
+/// Estimates the cyclomatic complexity of a function `body`: one plus the
+/// number of branches, loops, additional match arms, `&&`, `||` and `?`.
+pub fn complexity(body: &str) -> usize {
+    let code = tokenize(body)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Comment)
+        .collect::<Vec<_>>();
+    let mut complexity: usize = 1;
+    let mut matches = 0;
+    for (i, token) in code.iter().enumerate() {
+        let text = &body[token.range.clone()];
+        // The next character if it directly follows, for two-character operators.
+        let next = code
+            .get(i + 1)
+            .filter(|next| next.range.start == token.range.end)
+            .map_or("", |next| &body[next.range.clone()]);
+        match (token.kind, text, next) {
+            (TokenKind::Ident, "if" | "while" | "for" | "loop", _) => complexity += 1,
+            (TokenKind::Ident, "match", _) => matches += 1,
+            (TokenKind::Punct, "=", ">") | (TokenKind::Punct, "&", "&") => complexity += 1,
+            (TokenKind::Punct, "|", "|") | (TokenKind::Punct, "?", _) => complexity += 1,
+            _ => {}
+        }
+    }
+    // A match with n arms adds n - 1 paths.
+    complexity.saturating_sub(matches).max(1)
+}
+
+/// Replaces the bodies of the structs, enums and unions whose name or path
+/// matches one of `patterns`, and of the `impl` blocks of those types, with a
+/// [`marker`] rendered from `template`. Their documentation and headers
+/// remain, and unit and tuple structs are left as they are.
+pub fn elide_types(source: &str, template: &str, patterns: &[&str]) -> String {
+    let is_type = |kind| matches!(kind, ItemKind::Struct | ItemKind::Enum | ItemKind::Union);
+    // The range of each elided body, from its opening brace.
+    let mut ranges = items(source)
+        .into_iter()
+        .filter(|item| is_type(item.kind) && patterns.iter().any(|p| item.matches(p)))
+        .map(|item| item.range)
+        .chain(
+            impls(source)
+                .into_iter()
+                .filter(|i| {
+                    let name = i.name.as_deref().unwrap_or("");
+                    patterns.iter().any(|p| glob(p, name))
+                })
+                .map(|i| i.range),
+        )
+        .filter_map(|range| Some(body_start(source, range.clone())?..range.end))
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|range| range.start);
+    let mut elided = String::with_capacity(source.len());
+    let mut copied = 0;
+    for range in ranges {
+        if range.start < copied {
+            continue;
+        }
+        elided.push_str(source[copied..range.start].trim_end_matches(' '));
+        elided.push(' ');
+        elided.push_str(&marker(template, &source[range.clone()]));
+        copied = range.end;
+    }
+    elided.push_str(&source[copied..]);
+    elided
+}
+
+/// Returns the offset of the brace opening the body of the type or `impl`
+/// block in `range` of `source`, skipping its attributes, generics and
+/// `where` clause, or `None` if it has no body in braces.
+fn body_start(source: &str, range: Range<usize>) -> Option<usize> {
+    let item = &source[range.clone()];
+    let code = tokenize(item)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Comment)
+        .collect::<Vec<_>>();
+    let text = |i: usize| &item[code[i].range.clone()];
+    let keyword = (0..code.len()).find(|&i| {
+        code[i].kind == TokenKind::Ident && matches!(text(i), "struct" | "enum" | "union" | "impl")
+    })?;
+    let mut angle = 0usize;
+    let mut depth = 0usize;
+    let mut previous = "";
+    for token in &code[keyword + 1..] {
+        let text = &item[token.range.clone()];
+        match text {
+            "<" => angle += 1,
+            // Not the arrow of a return type such as `Fn() -> T`.
+            ">" if previous != "-" => angle = angle.saturating_sub(1),
+            "(" | "[" => depth += 1,
+            ")" | "]" => depth = depth.saturating_sub(1),
+            "{" if angle == 0 && depth == 0 => return Some(range.start + token.range.start),
+            ";" if angle == 0 && depth == 0 => return None,
+            _ => {}
+        }
+        previous = text;
+    }
+    None
+}
+
+/// Returns the source of all items selected by `pick`, separated by empty lines.
+pub fn extract(source: &str, pick: impl Fn(&Item) -> bool) -> String {
+    let mut extracted = String::new();
+    let mut end = 0;
+    for item in items(source) {
+        if item.range.start < end || !pick(&item) {
+            continue;
+        }
+        if !extracted.is_empty() {
+            extracted.push('\n');
+        }
+        extracted.push_str(&source[item.range.clone()]);
+        extracted.push('\n');
+        end = item.range.end;
+    }
+    extracted
+}
+
+/// Finds the functions, structs, enums, unions and traits in `source`, in order
+/// of appearance.
+pub fn items(source: &str) -> Vec<Item> {
+    let code = tokenize(source)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Comment)
+        .collect::<Vec<_>>();
+    let text = |i: usize| code.get(i).map_or("", |t: &Token| &source[t.range.clone()]);
+    let ident = |i: usize| Some(text(i)).filter(|_| code[i].kind == TokenKind::Ident);
+
+    let mut items = Vec::new();
+    let mut scopes: Vec<(String, usize)> = Vec::new();
+    let mut pending_scope = None;
+    let mut depth = 0;
+    let mut i = 0;
+    while i < code.len() {
+        match (code[i].kind, text(i)) {
+            (TokenKind::Punct, "{") => {
+                depth += 1;
+                if let Some(name) = pending_scope.take() {
+                    scopes.push((name, depth));
+                }
+            }
+            (TokenKind::Punct, "}") => {
+                if scopes.last().is_some_and(|(_, d)| *d == depth) {
+                    scopes.pop();
+                }
+                depth = depth.saturating_sub(1);
+            }
+            (TokenKind::Punct, ";") => pending_scope = None,
+            (TokenKind::Ident, "mod") if i + 1 < code.len() => {
+                pending_scope = ident(i + 1).map(str::to_string);
+            }
+            (TokenKind::Ident, "impl") => {
+                let (name, _, open) = impl_header(&code, source, i + 1);
+                pending_scope = name;
+                i = open;
+                continue;
+            }
+            (TokenKind::Ident, keyword @ ("fn" | "struct" | "enum" | "union" | "trait"))
+                if i + 1 < code.len() && ident(i + 1).is_some() =>
+            {
+                let kind = match keyword {
+                    "fn" => ItemKind::Fn,
+                    "struct" => ItemKind::Struct,
+                    "enum" => ItemKind::Enum,
+                    "union" => ItemKind::Union,
+                    _ => ItemKind::Trait,
+                };
+                let name = text(i + 1).to_string();
+                let Some((open, end)) = item_end(&code, source, i + 2) else {
+                    break;
+                };
+                let (first, public) = qualifiers(&code, source, i);
+                let body = open
+                    .filter(|_| kind == ItemKind::Fn)
+                    .map(|open| code[open].range.start..code[end].range.end);
+                let signature_end = body.as_ref().map_or(code[end].range.end, |body| body.start);
+                items.push(Item {
+                    kind,
+                    name: name.clone(),
+                    scope: scopes.iter().map(|(name, _)| name.clone()).collect(),
+                    range: item_start(&code, source, first)..code[end].range.end,
+                    signature: code[first].range.start..signature_end,
+                    body,
+                    public,
+                });
+                match (kind, open) {
+                    // Continue inside the body to find nested items.
+                    (ItemKind::Fn, Some(open)) => {
+                        i = open;
+                        continue;
+                    }
+                    (ItemKind::Trait, Some(open)) => {
+                        pending_scope = Some(name);
+                        i = open;
+                        continue;
+                    }
+                    _ => i = end,
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    items
+}
+
+/// An `impl` block found by [`impls`].
+pub struct Impl {
+    /// The name of the implemented type, without generic arguments.
+    pub name: Option<String>,
+    /// The path of the implemented trait as written, such as `serde::Serialize`,
+    /// without generic arguments.
+    pub implemented: Option<String>,
+    /// The whole block, including the attributes directly above it.
+    pub range: Range<usize>,
+    /// Whether the block is nested in a block other than a module, such as a
+    /// `const _: () = { ... };` block generated by a macro.
+    pub nested: bool,
+}
+
+/// Finds the `impl` blocks in `source` at any depth, in order of appearance.
+pub fn impls(source: &str) -> Vec<Impl> {
+    let code = tokenize(source)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Comment)
+        .collect::<Vec<_>>();
+    let text = |i: usize| &source[code[i].range.clone()];
+    let mut impls = Vec::new();
+    // Whether each enclosing brace opens a module.
+    let mut braces = Vec::new();
+    for i in 0..code.len() {
+        match text(i) {
+            "{" => braces.push(i >= 2 && text(i - 2) == "mod"),
+            "}" => {
+                braces.pop();
+            }
+            // Not `impl Trait` in the type of an argument or return value.
+            "impl"
+                if i == 0
+                    || matches!(text(i - 1), "{" | "}" | ";" | "]" | "unsafe" | "default") =>
+            {
+                let (name, implemented, open) = impl_header(&code, source, i + 1);
+                if open >= code.len() || text(open) != "{" {
+                    continue;
+                }
+                let Some(end) = matching(&code, source, open, "{", "}") else {
+                    continue;
+                };
+                let (first, _) = qualifiers(&code, source, i);
+                impls.push(Impl {
+                    name,
+                    implemented,
+                    range: item_start(&code, source, first)..code[end].range.end,
+                    nested: braces.iter().any(|&is_mod| !is_mod),
+                });
+            }
+            _ => {}
+        }
+    }
+    impls
+}
+
+/// Returns the name of the implemented type of an `impl` header starting at
+/// token `i`, the path of the implemented trait, if any, and the index of the
+/// token ending the header.
+fn impl_header(
+    code: &[Token],
+    source: &str,
+    mut i: usize,
+) -> (Option<String>, Option<String>, usize) {
+    let mut name = None;
+    let mut path = String::new();
+    let mut implemented = None;
+    let mut angle = 0usize;
+    let mut in_where = false;
+    while i < code.len() {
+        let text = &source[code[i].range.clone()];
+        match (code[i].kind, text) {
+            (TokenKind::Punct, "{" | ";") => break,
+            (TokenKind::Punct, "<") => angle += 1,
+            // Not the arrow of a return type such as `Fn() -> T`.
+            (TokenKind::Punct, ">") if &source[code[i - 1].range.clone()] != "-" => {
+                angle = angle.saturating_sub(1)
+            }
+            (TokenKind::Ident, "for") if angle == 0 => {
+                name = None;
+                implemented = Some(std::mem::take(&mut path));
+            }
+            (TokenKind::Ident, "where") => in_where = true,
+            (TokenKind::Ident, "dyn" | "mut" | "unsafe" | "const") => {}
+            (TokenKind::Ident, ident) if angle == 0 && !in_where => {
+                name = Some(ident.to_string());
+                path.push_str(ident);
+            }
+            (TokenKind::Punct, ":") if angle == 0 && !in_where => path.push(':'),
+            _ => {}
+        }
+        i += 1;
+    }
+    (name, implemented, i)
+}
+
+/// Returns the indices of the opening brace, if any, and the token ending an
+/// item whose name is followed by token `i`: its closing brace or a semicolon.
+fn item_end(code: &[Token], source: &str, mut i: usize) -> Option<(Option<usize>, usize)> {
+    let mut depth = 0usize;
+    while i < code.len() {
+        match &source[code[i].range.clone()] {
+            "(" | "[" => depth += 1,
+            ")" | "]" => depth = depth.saturating_sub(1),
+            ";" if depth == 0 => return Some((None, i)),
+            "{" if depth == 0 => return Some((Some(i), matching(code, source, i, "{", "}")?)),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the index of the token balancing the `open` token at index `i`,
+/// searching backwards if `open` is a closing delimiter.
+fn matching(code: &[Token], source: &str, i: usize, open: &str, close: &str) -> Option<usize> {
+    let backwards = matches!(open, ")" | "]" | "}");
+    let mut indices: Box<dyn Iterator<Item = usize>> = if backwards {
+        Box::new((0..=i).rev())
+    } else {
+        Box::new(i..code.len())
+    };
+    let mut depth = 0usize;
+    indices.find(|&j| {
+        let text = &source[code[j].range.clone()];
+        if text == open {
+            depth += 1;
+        } else if text == close {
+            depth -= 1;
+        }
+        depth == 0
+    })
+}
+
+/// Returns the index of the first qualifier of the item with its keyword at
+/// token `i`, and whether the item is declared `pub` without restrictions.
+fn qualifiers(code: &[Token], source: &str, mut i: usize) -> (usize, bool) {
+    let text = |i: usize| &source[code[i].range.clone()];
+    let mut public = false;
+    while i > 0 {
+        let prev = i - 1;
+        match (code[prev].kind, text(prev)) {
+            (TokenKind::Ident, "pub") => {
+                public = true;
+                i = prev;
+            }
+            (TokenKind::Ident, qualifier) if QUALIFIERS.contains(&qualifier) => i = prev,
+            // The ABI of `extern "C"`.
+            (TokenKind::Literal, _) => i = prev,
+            // Restricted visibility such as `pub(crate)`.
+            (TokenKind::Punct, ")") => match matching(code, source, prev, ")", "(") {
+                Some(open) if open > 0 && text(open - 1) == "pub" => i = open - 1,
+                _ => break,
+            },
+            _ => break,
+        }
+    }
+    (i, public)
+}
+
+/// Returns where the item with its first qualifier at token `i` starts,
+/// including its attributes and the comment lines directly above it.
+fn item_start(code: &[Token], source: &str, mut i: usize) -> usize {
+    let text = |i: usize| &source[code[i].range.clone()];
+    while i > 0 && text(i - 1) == "]" {
+        match matching(code, source, i - 1, "]", "[") {
+            Some(open) if open > 0 && text(open - 1) == "#" => i = open - 1,
+            _ => break,
+        }
+    }
+    let mut start = code[i].range.start;
+    let mut line_start = source[..start].rfind('\n').map_or(0, |n| n + 1);
+    if !source[line_start..start].trim().is_empty() {
+        return start;
+    }
+    start = line_start;
+    while line_start > 0 {
+        let prev_start = source[..line_start - 1].rfind('\n').map_or(0, |n| n + 1);
+        let line = source[prev_start..line_start - 1].trim();
+        if !line.starts_with("//") || line.starts_with("//!") {
+            break;
+        }
+        start = prev_start;
+        line_start = prev_start;
+    }
+    start
+}
+
+/// Returns the code of `source` with comments removed and all whitespace
+/// collapsed into single spaces, so that formatting changes do not matter.
+pub fn normalize(source: &str) -> String {
+    let mut normalized = String::new();
+    let mut end = None;
+    for token in tokenize(source) {
+        if token.kind == TokenKind::Comment {
+            continue;
+        }
+        if end.is_some_and(|end| end < token.range.start) {
+            normalized.push(' ');
+        }
+        normalized.push_str(&source[token.range.clone()]);
+        end = Some(token.range.end);
+    }
+    normalized
+}
+
+/// Returns the offsets of the paths of two or more segments in `source`, with
+/// their first segment, such as `serde` of `serde::Serialize` or of
+/// `::serde::Serialize`. Paths in comments and literals and paths continuing
+/// others, such as `new` of `Vec::<u8>::new`, are left out.
+pub fn path_roots(source: &str) -> Vec<(usize, &str)> {
+    let code = tokenize(source)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Comment)
+        .collect::<Vec<_>>();
+    let text = |i: usize| code.get(i).map_or("", |t: &Token| &source[t.range.clone()]);
+    let mut roots = Vec::new();
+    for (i, token) in code.iter().enumerate() {
+        if token.kind != TokenKind::Ident || text(i + 1) != ":" || text(i + 2) != ":" {
+            continue;
+        }
+        // A leading `::` only starts a path if nothing it could continue precedes it.
+        let continues = i >= 3
+            && text(i - 1) == ":"
+            && text(i - 2) == ":"
+            && (code[i - 3].kind == TokenKind::Ident || text(i - 3) == ">");
+        if !continues {
+            roots.push((token.range.start, text(i)));
+        }
+    }
+    roots
+}
+
+/// Returns the ranges of the string, character and number literals in `source`.
+pub fn literals(source: &str) -> Vec<Range<usize>> {
+    tokenize(source)
+        .into_iter()
+        .filter(|t| t.kind == TokenKind::Literal)
+        .map(|t| t.range)
+        .collect()
+}
+
+/// Matches `text` against `pattern`, where `*` matches any sequence of characters.
+pub fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| glob(rest, &text[i..]))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum TokenKind {
+    Ident,
+    Lifetime,
+    Literal,
+    Punct,
+    Comment,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    range: Range<usize>,
+}
+
+/// Splits `source` into tokens, just precisely enough to tell code apart from
+/// comments and literals. Every punctuation character is a token of its own.
+fn tokenize(source: &str) -> Vec<Token> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let rest = &source[i..];
+        let kind = if bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+            TokenKind::Comment
+        } else if rest.starts_with("/*") {
+            i = block_comment_end(bytes, i);
+            TokenKind::Comment
+        } else if let Some(end) = literal_end(bytes, i) {
+            i = end;
+            TokenKind::Literal
+        } else if bytes[i] == b'\'' {
+            i += 1;
+            while i < bytes.len() && is_ident_continue(bytes[i]) {
+                i += 1;
+            }
+            TokenKind::Lifetime
+        } else if is_ident_continue(bytes[i]) {
+            let numeric = bytes[i].is_ascii_digit();
+            if rest.starts_with("r#") {
+                i += 2;
+            }
+            while i < bytes.len() && is_ident_continue(bytes[i]) {
+                i += 1;
+            }
+            if numeric {
+                TokenKind::Literal
+            } else {
+                TokenKind::Ident
+            }
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            TokenKind::Punct
+        };
+        tokens.push(Token {
+            kind,
+            range: start..i,
+        });
+    }
+    tokens
+}
+
+fn is_ident_continue(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
+
+fn block_comment_end(bytes: &[u8], mut i: usize) -> usize {
+    let mut depth = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                break;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    i.min(bytes.len())
+}
+
+/// Returns the end of the string, byte string, raw string or character
+/// literal starting at `i`, if any.
+fn literal_end(bytes: &[u8], i: usize) -> Option<usize> {
+    let mut j = i;
+    // The prefixes `b`, `c`, `r`, `br` and `cr`.
+    if matches!(bytes[j], b'b' | b'c') {
+        j += 1;
+    }
+    let raw = bytes.get(j) == Some(&b'r');
+    if raw {
+        j += 1;
+    }
+    if j > i && i > 0 && is_ident_continue(bytes[i - 1]) {
+        return None;
+    }
+    if raw {
+        let hashes = bytes[j..].iter().take_while(|&&b| b == b'#').count();
+        j += hashes;
+        if bytes.get(j) != Some(&b'"') {
+            return None;
+        }
+        j += 1;
+        while j < bytes.len() {
+            if bytes[j] == b'"'
+                && bytes[j + 1..]
+                    .iter()
+                    .take(hashes)
+                    .filter(|&&b| b == b'#')
+                    .count()
+                    == hashes
+            {
+                return Some(j + 1 + hashes);
+            }
+            j += 1;
+        }
+        return Some(bytes.len());
+    }
+    match bytes.get(j) {
+        Some(b'"') => Some(quoted_end(bytes, j + 1, b'"')),
+        Some(b'\'') if bytes.get(j + 1) == Some(&b'\\') => Some(quoted_end(bytes, j + 1, b'\'')),
+        Some(b'\'') => {
+            // A character literal rather than a lifetime if a quote follows the character.
+            let len = match bytes.get(j + 1)? {
+                0xf0.. => 4,
+                0xe0.. => 3,
+                0xc0.. => 2,
+                _ => 1,
+            };
+            (bytes.get(j + 1 + len) == Some(&b'\'')).then_some(j + 2 + len)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the position after the unescaped `quote` closing a literal whose
+/// contents start at `i`.
+fn quoted_end(bytes: &[u8], mut i: usize, quote: u8) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+/// A parser.
+pub struct Parser<'a> {
+    input: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn next(&mut self) -> Option<char> {
+        let c = '{';
+        self.input.chars().next().filter(|&x| x != c)
+    }
+}
+
+mod tests {
+    fn parses() {
+        // A brace in a comment: {
+        let s = \"}\";
+    }
+}
+";
+
+    #[test]
+    fn items_are_found_with_their_scope() {
+        let items = items(SOURCE);
+        let found = items
+            .iter()
+            .map(|item| (item.kind, item.path(), item.public))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (ItemKind::Struct, "Parser".to_string(), true),
+                (ItemKind::Fn, "Parser::next".to_string(), false),
+                (ItemKind::Fn, "tests::parses".to_string(), false),
+            ]
+        );
+        assert!(SOURCE[items[0].range.clone()].starts_with("/// A parser."));
+        assert!(SOURCE[items[1].signature.clone()].starts_with("pub(crate) fn next"));
+    }
+
+    #[test]
+    fn impls_are_found_with_their_type() {
+        let impls = impls(SOURCE);
+        assert_eq!(impls.len(), 1);
+        assert_eq!(impls[0].name.as_deref(), Some("Parser"));
+        assert_eq!(impls[0].implemented, None);
+    }
+
+    #[test]
+    fn bodies_are_elided_unless_kept() {
+        let elided = elide_bodies(SOURCE, MARKER, |item| item.name == "parses");
+        assert!(elided.contains("pub(crate) fn next(&mut self) -> Option<char> { /* ... */ }\n"));
+        assert!(elided.contains("let s = \"}\";"));
+    }
+
+    #[test]
+    fn types_keep_their_docs_and_attributes() {
+        let source = "\
+/// A generated machine (see docs).
+#[cfg(feature = \"generated\")]
+impl GeneratedMachine {
+    fn step(&mut self) {}
+}
+";
+        assert_eq!(
+            elide_types(source, MARKER, &["Generated*"]),
+            "\
+/// A generated machine (see docs).
+#[cfg(feature = \"generated\")]
+impl GeneratedMachine { /* ... */ }
+"
+        );
+    }
+
+    #[test]
+    fn types_keep_their_generics() {
+        let source = "\
+impl<F: Fn(u8)> GeneratedWrapper<F> where F: Fn(u8) -> u8 {
+    fn call(&self) {}
+}
+pub(crate) struct GeneratedState {
+    state: u8,
+}
+";
+        assert_eq!(
+            elide_types(source, MARKER, &["Generated*"]),
+            "\
+impl<F: Fn(u8)> GeneratedWrapper<F> where F: Fn(u8) -> u8 { /* ... */ }
+pub(crate) struct GeneratedState { /* ... */ }
+"
+        );
+    }
+
+    #[test]
+    fn types_without_a_body_are_left_alone() {
+        let source = "pub struct GeneratedMachine;\npub struct GeneratedId(u32);\n";
+        assert_eq!(elide_types(source, MARKER, &["Generated*"]), source);
+    }
+}
@@ -0,0 +1,247 @@
+use std::io::Error;
+use std::io::Write;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::budget::Part;
+use crate::cli::Args;
+use crate::config::Config;
+use crate::dump;
+use crate::elide;
+use crate::manifest::Tracked;
+use crate::project::Project;
+
+/// Lines a snippet may span at most.
+const MAX_LINES: usize = 20;
+
+/// Lines above a match arm searched for the start of the `match`.
+const MATCH_LOOKBACK: usize = 10;
+
+/// How a type is used at a site, in order of preference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Site {
+    Construction,
+    Match,
+}
+
+/// Writes up to `args.examples_count` snippets showing how each of the types
+/// in `args.examples` is constructed and matched on across the project,
+/// outside of its definition and its own `impl` blocks.
+pub fn write_examples(
+    project: &Project,
+    args: &Args,
+    config: &Config,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let mut sources = Vec::new();
+    for file in dump::project_files(project, args) {
+        if file.extension().is_some_and(|ext| ext == "rs") {
+            let source = std::fs::read_to_string(&file)?;
+            sources.push((file, source));
+        }
+    }
+    for name in &args.examples {
+        let mut sites: Vec<(Site, &PathBuf, Range<usize>)> = Vec::new();
+        for (file, source) in &sources {
+            let mut taken: Vec<Range<usize>> = Vec::new();
+            for (site, line) in occurrences(source, name) {
+                if taken.iter().any(|lines| lines.contains(&line)) {
+                    continue;
+                }
+                let lines = snippet(source, line, site);
+                taken.push(lines.clone());
+                sites.push((site, file, lines));
+            }
+        }
+        // Constructions first, otherwise in the order of the files.
+        sites.sort_by_key(|(site, _, _)| *site);
+        if sites.is_empty() {
+            continue;
+        }
+        let name = writer.anonymized(name);
+        writeln!(writer, "// How `{name}` is used:")?;
+        let template = config.file_header(args.format);
+        for (_, file, lines) in sites.into_iter().take(args.examples_count) {
+            let source = &sources
+                .iter()
+                .find(|(f, _)| f == file)
+                .expect("File is read")
+                .1;
+            let mut text = source
+                .lines()
+                .skip(lines.start)
+                .take(lines.len())
+                .collect::<Vec<_>>()
+                .join("\n");
+            text.push('\n');
+            let header = format!("{}:{}", project.header_path(file), lines.start + 1);
+            let header = writer.anonymized(&header);
+            let text = writer.anonymized(&text);
+            let text = writer.fit(Part::Code, text, true, config.elision_marker());
+            writer.section(&header, file, source.as_bytes(), &text, |writer, _| {
+                args.format.write_file(template, &header, &text, writer)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the zero-based lines where `name` is constructed or matched on in
+/// `source`, outside of its definition and its own `impl` blocks.
+fn occurrences(source: &str, name: &str) -> Vec<(Site, usize)> {
+    let own = elide::items(source)
+        .into_iter()
+        .filter(|item| item.name == name)
+        .map(|item| item.range)
+        .chain(
+            elide::impls(source)
+                .into_iter()
+                .filter(|i| i.name.as_deref() == Some(name))
+                .map(|i| i.range),
+        )
+        .collect::<Vec<_>>();
+    let mut occurrences = Vec::new();
+    let mut offset = 0;
+    for (n, line) in source.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+        let code = line.trim_start();
+        if code.starts_with("//") || code.starts_with("use ") || code.starts_with("pub use ") {
+            continue;
+        }
+        if own.iter().any(|range| range.contains(&start)) {
+            continue;
+        }
+        // A function returning the type is not a construction.
+        let returns = format!("-> {name} {{");
+        let constructs = [" {", "(", "::new(", "::default(", "::from("]
+            .iter()
+            .any(|suffix| contains_word(&line.replace(&returns, ""), name, suffix));
+        if line.contains("=>") && contains_word(line, name, "::") {
+            occurrences.push((Site::Match, n));
+        } else if constructs {
+            occurrences.push((Site::Construction, n));
+        }
+    }
+    occurrences
+}
+
+/// Returns true if `line` contains `name` as a whole word directly followed by `suffix`.
+fn contains_word(line: &str, name: &str, suffix: &str) -> bool {
+    let pattern = format!("{name}{suffix}");
+    line.match_indices(&pattern)
+        .any(|(i, _)| !line[..i].ends_with(|c: char| c.is_alphanumeric() || c == '_'))
+}
+
+/// Returns the zero-based lines of the snippet around the site at `line`:
+/// the statement or expression starting there, or the whole `match` around
+/// a match arm, until its brackets are balanced.
+fn snippet(source: &str, line: usize, site: Site) -> Range<usize> {
+    let lines = source.lines().collect::<Vec<_>>();
+    let mut start = line;
+    if site == Site::Match {
+        if let Some(above) = (line.saturating_sub(MATCH_LOOKBACK)..=line)
+            .rev()
+            .find(|&n| lines[n].contains("match "))
+        {
+            start = above;
+        }
+    }
+    let mut depth = 0isize;
+    let mut end = start;
+    while end < lines.len() && end - start < MAX_LINES {
+        for c in lines[end].chars() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        end += 1;
+        if depth <= 0 && end > line {
+            break;
+        }
+    }
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+use crate::Point;
+
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl Point {
+    pub fn new() -> Point {
+        Point { x: 0, y: 0 }
+    }
+}
+
+fn origin() -> Point {
+    // Point { x: 1, y: 1 }
+    let p = Point {
+        x: 0,
+        y: 0,
+    };
+    let q = Point::new();
+    let r = APoint { x: 0 };
+    p
+}
+
+fn kind(shape: Shape) -> u8 {
+    match shape {
+        Shape::Circle(_) => 0,
+        Shape::Square(_) => 1,
+    }
+}
+";
+
+    #[test]
+    fn constructions_outside_of_the_definition_are_found() {
+        assert_eq!(
+            occurrences(SOURCE, "Point"),
+            [(Site::Construction, 15), (Site::Construction, 19)]
+        );
+        assert_eq!(snippet(SOURCE, 15, Site::Construction), 15..19);
+        assert_eq!(snippet(SOURCE, 19, Site::Construction), 19..20);
+    }
+
+    #[test]
+    fn match_arms_are_found_with_their_match() {
+        assert_eq!(
+            occurrences(SOURCE, "Shape"),
+            [(Site::Match, 26), (Site::Match, 27)]
+        );
+        assert_eq!(snippet(SOURCE, 26, Site::Match), 25..29);
+    }
+
+    #[test]
+    fn crlf_sources_have_the_same_sites() {
+        let crlf = SOURCE.replace('\n', "\r\n");
+        assert_eq!(occurrences(&crlf, "Point"), occurrences(SOURCE, "Point"));
+        assert_eq!(occurrences(&crlf, "Shape"), occurrences(SOURCE, "Shape"));
+        // The first line after an `impl` block is not taken as part of it.
+        let source =
+            "impl Point {\r\n    fn a() {}\r\n    fn b() {}\r\n    fn c() {}\r\n}\r\nPoint(1);\r\n";
+        assert_eq!(occurrences(source, "Point"), [(Site::Construction, 5)]);
+    }
+
+    #[test]
+    fn names_are_matched_as_whole_words() {
+        assert!(contains_word("let p = Point {", "Point", " {"));
+        assert!(!contains_word("let p = APoint {", "Point", " {"));
+        assert!(!contains_word("let p = Point_ {", "Point", " {"));
+        assert!(contains_word(
+            "(Point::new(), APoint::new())",
+            "Point",
+            "::new("
+        ));
+    }
+}
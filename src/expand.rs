@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::path::Path;
+use std::process::Stdio;
+
+use crate::elide;
+use crate::progress;
+use crate::toolchain;
+
+/// The code generated by macros for an item.
+pub struct Expansion {
+    /// The expanded item itself, in case an attribute macro changed it.
+    pub item: Option<String>,
+    /// The `impl` blocks generated for the item by derives and other macros.
+    pub impls: Vec<String>,
+}
+
+/// Expands the macros of a crate of the package with `manifest` through rustc
+/// and returns the code generated for each of the items named `names`.
+/// `target` selects the crate, such as `["--lib"]`.
+pub fn expand(
+    manifest: &Path,
+    target: &[&str],
+    names: &[String],
+) -> Result<BTreeMap<String, Expansion>, Error> {
+    progress::step(&format!("Expanding macros of {}", manifest.display()));
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    let mut command = toolchain::nightly_cargo(dir).ok_or_else(|| {
+        Error::other(
+            "--expand-derives needs a nightly toolchain for `-Zunpretty=expanded`. \
+             Install one with `rustup toolchain install nightly`.",
+        )
+    })?;
+    command
+        .arg("rustc")
+        .arg("--manifest-path")
+        .arg(manifest)
+        .arg("--profile=check")
+        .args(target)
+        .args(["--", "-Zunpretty=expanded"])
+        .stderr(Stdio::piped());
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::other(format!(
+            "Failed to expand the macros of {}:\n{stderr}",
+            manifest.display()
+        )));
+    }
+    let expanded = String::from_utf8_lossy(&output.stdout);
+
+    let mut expansions = BTreeMap::new();
+    for name in names {
+        let item = elide::items(&expanded)
+            .into_iter()
+            .find(|item| &item.name == name)
+            .map(|item| expanded[item.signature.start..item.range.end].to_string());
+        // Hand-written impls of the type are already in the sources, while
+        // generated ones are marked as derived or hidden in a block.
+        let impls = elide::impls(&expanded)
+            .into_iter()
+            .filter(|i| i.name.as_ref() == Some(name))
+            .filter(|i| i.nested || expanded[i.range.clone()].contains("automatically_derived"))
+            .map(|i| expanded[i.range].to_string())
+            .collect();
+        expansions.insert(name.clone(), Expansion { item, impls });
+    }
+    Ok(expansions)
+}
+
+/// Inserts the generated code of the items in `source` with an expansion
+/// right after their definition.
+pub fn insert(source: &str, expansions: &BTreeMap<String, Expansion>) -> String {
+    let mut inserted = String::with_capacity(source.len());
+    let mut copied = 0;
+    for item in elide::items(source) {
+        let Some(expansion) = expansions.get(&item.name) else {
+            continue;
+        };
+        if item.range.start < copied {
+            continue;
+        }
+        let original = &source[item.signature.start..item.range.end];
+        let generated = expansion
+            .item
+            .iter()
+            .filter(|expanded| !same(expanded, original))
+            .chain(&expansion.impls)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if generated.is_empty() {
+            continue;
+        }
+        inserted.push_str(&source[copied..item.range.end]);
+        inserted.push_str(&format!(
+            "\n\n// Generated by the macros on `{}`:\n{generated}",
+            item.name
+        ));
+        copied = item.range.end;
+    }
+    inserted.push_str(&source[copied..]);
+    inserted
+}
+
+/// Returns true if two items only differ in formatting, comments and trailing
+/// commas, which the pretty-printer of rustc changes.
+fn same(a: &str, b: &str) -> bool {
+    let strip = |code: &str| {
+        elide::normalize(code)
+            .replace(' ', "")
+            .replace(",}", "}")
+            .replace(",)", ")")
+    };
+    strip(a) == strip(b)
+}
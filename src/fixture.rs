@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::cli::Args;
+use crate::cli::Cargo;
+use crate::temp;
+
+/// A directory of files for a test, removed when dropped.
+pub struct Fixture {
+    pub dir: PathBuf,
+}
+
+impl Fixture {
+    /// Creates an empty directory unique to this test.
+    pub fn new() -> Fixture {
+        let dir = temp::create_dir("cargo-gpt-test").unwrap();
+        Fixture { dir }
+    }
+
+    /// Writes `content` to `path` relative to the fixture, creating its directories.
+    pub fn write(&self, path: &str, content: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Parses the options of `cargo gpt` given in `args`.
+pub fn args(args: &[&str]) -> Args {
+    let Cargo::Gpt(args) = Cargo::parse_from(["cargo", "gpt"].iter().chain(args));
+    args
+}
@@ -0,0 +1,357 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::config::Config;
+use crate::tokens;
+
+/// How files are delimited in a dump.
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Each file is preceded by a `// path` comment.
+    #[default]
+    Plain,
+    /// Each file is a fenced code block starting with a `// path` comment.
+    Markdown,
+    /// Each file is wrapped in a `<file path="...">` element holding its
+    /// content in a CDATA section.
+    Xml,
+}
+
+/// A file of a dump, or the text between files if it has no path.
+pub struct Section {
+    pub path: Option<String>,
+    pub content: String,
+}
+
+impl Format {
+    /// The template of the line introducing a file, unless configured otherwise.
+    pub fn default_header(self) -> &'static str {
+        match self {
+            Format::Plain | Format::Markdown => "// {path}",
+            Format::Xml => "<file path=\"{path}\">",
+        }
+    }
+
+    /// Renders the header `template` for a file, substituting `{path}`, and
+    /// `{lines}` and `{tokens}` by the size of its content.
+    pub fn header(self, template: &str, path: &str, content: &str) -> String {
+        let path = match self {
+            Format::Xml => path
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;"),
+            _ => path.to_string(),
+        };
+        template
+            .replace("{lines}", &content.lines().count().to_string())
+            .replace("{tokens}", &tokens::estimate(content).to_string())
+            .replace("{path}", &path)
+    }
+
+    pub fn write_file(
+        self,
+        template: &str,
+        path: &str,
+        content: &str,
+        writer: &mut impl Write,
+    ) -> Result<(), Error> {
+        let header = self.header(template, path, content);
+        let newline = if content.is_empty() || content.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        match self {
+            Format::Plain => write!(writer, "{header}\n{content}{newline}"),
+            Format::Markdown => {
+                let lang = Path::new(path)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("");
+                // The fence must be longer than any backtick run in the content.
+                let mut fence = "```".to_string();
+                while content.contains(&fence) {
+                    fence.push('`');
+                }
+                write!(
+                    writer,
+                    "{fence}{lang}\n{header}\n{content}{newline}{fence}\n"
+                )
+            }
+            Format::Xml => {
+                // A `]]>` in the content is split across two CDATA sections.
+                let content = content.replace("]]>", CDATA_SPLIT);
+                write!(
+                    writer,
+                    "{header}\n<![CDATA[\n{content}{newline}]]>\n</file>\n"
+                )
+            }
+        }
+    }
+
+    pub fn write_section(
+        self,
+        config: &Config,
+        section: &Section,
+        writer: &mut impl Write,
+    ) -> Result<(), Error> {
+        match &section.path {
+            Some(path) => self.write_file(config.file_header(self), path, &section.content, writer),
+            None => writer.write_all(section.content.as_bytes()),
+        }
+    }
+}
+
+/// How `]]>` is written inside a CDATA section.
+const CDATA_SPLIT: &str = "]]]]><![CDATA[>";
+
+/// Splits a dump in any format into its sections, recognizing file headers
+/// by the templates configured for each format.
+pub fn parse(dump: &str, config: &Config) -> Vec<Section> {
+    let plain = Template::new(config.file_header(Format::Plain));
+    let markdown = Template::new(config.file_header(Format::Markdown));
+    let xml = Template::new(config.file_header(Format::Xml));
+    let mut sections: Vec<Section> = Vec::new();
+    // The line closing the current section, if it is not closed by the next header.
+    let mut close: Option<String> = None;
+    let mut lines = dump.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_end();
+        let in_file = sections.last().is_some_and(|s| s.path.is_some());
+        if close.as_deref() == Some(trimmed) && trimmed == "]]>" {
+            if let Some(section) = sections.last_mut() {
+                section.content = section.content.replace(CDATA_SPLIT, "]]>");
+            }
+            close = Some("</file>".to_string());
+            continue;
+        }
+        if close.as_deref() == Some(trimmed) {
+            close = None;
+            sections.push(Section {
+                path: None,
+                content: String::new(),
+            });
+            continue;
+        }
+        if close.is_none() || !in_file {
+            if let Some(path) = xml.path(trimmed) {
+                let path = path
+                    .replace("&lt;", "<")
+                    .replace("&quot;", "\"")
+                    .replace("&amp;", "&");
+                // Dumps written before CDATA was used hold the content as is.
+                let cdata = lines.next_if(|next| next.trim_end() == "<![CDATA[");
+                close = Some(if cdata.is_some() { "]]>" } else { "</file>" }.to_string());
+                sections.push(Section {
+                    path: Some(path),
+                    content: String::new(),
+                });
+                continue;
+            }
+            if trimmed.starts_with("```") {
+                if let Some(path) = lines
+                    .peek()
+                    .copied()
+                    .and_then(|next| markdown.path(next.trim_end()))
+                {
+                    let fence = trimmed.trim_end_matches(|c| c != '`').to_string();
+                    close = Some(fence);
+                    sections.push(Section {
+                        path: Some(path.to_string()),
+                        content: String::new(),
+                    });
+                    lines.next();
+                    continue;
+                }
+            }
+            if close.is_none() {
+                if let Some(path) = plain.path(trimmed) {
+                    sections.push(Section {
+                        path: Some(path.to_string()),
+                        content: String::new(),
+                    });
+                    continue;
+                }
+            }
+        }
+        match sections.last_mut() {
+            Some(section) => section.content.push_str(line),
+            None => sections.push(Section {
+                path: None,
+                content: line.to_string(),
+            }),
+        }
+    }
+    sections.retain(|s| s.path.is_some() || !s.content.is_empty());
+    sections
+}
+
+/// A parsed header template.
+struct Template<'a>(Vec<Segment<'a>>);
+
+enum Segment<'a> {
+    Literal(&'a str),
+    /// `{lines}` or `{tokens}`.
+    Number,
+    Path,
+}
+
+impl<'a> Template<'a> {
+    fn new(mut template: &'a str) -> Self {
+        let mut segments = Vec::new();
+        while !template.is_empty() {
+            let (segment, len) = if template.starts_with("{path}") {
+                (Segment::Path, "{path}".len())
+            } else if template.starts_with("{lines}") {
+                (Segment::Number, "{lines}".len())
+            } else if template.starts_with("{tokens}") {
+                (Segment::Number, "{tokens}".len())
+            } else {
+                // A literal runs to the next `{` after its first character,
+                // which may be longer than a byte.
+                let first = template.chars().next().map_or(0, char::len_utf8);
+                let len = template[first..]
+                    .find('{')
+                    .map_or(template.len(), |i| i + first);
+                (Segment::Literal(&template[..len]), len)
+            };
+            segments.push(segment);
+            template = &template[len..];
+        }
+        Template(segments)
+    }
+
+    /// Returns the path of a header line rendered from this template. Paths
+    /// which contain whitespace or do not look like a path are not headers,
+    /// since they are more likely ordinary comments.
+    fn path<'b>(&self, line: &'b str) -> Option<&'b str> {
+        let mut path = None;
+        let looks_like_path = |path: &str| path.contains('/') || path.contains('.');
+        (matches(&self.0, line, &mut path) && path.is_some_and(looks_like_path))
+            .then_some(path)
+            .flatten()
+    }
+}
+
+fn matches<'b>(segments: &[Segment], line: &'b str, path: &mut Option<&'b str>) -> bool {
+    match segments.split_first() {
+        None => line.is_empty(),
+        Some((Segment::Literal(literal), rest)) => line
+            .strip_prefix(literal)
+            .is_some_and(|line| matches(rest, line, path)),
+        Some((Segment::Number, rest)) => {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            (1..=digits).rev().any(|n| matches(rest, &line[n..], path))
+        }
+        Some((Segment::Path, rest)) => {
+            let word = line.find(char::is_whitespace).unwrap_or(line.len());
+            (1..=word)
+                .rev()
+                .filter(|&n| line.is_char_boundary(n))
+                .any(|n| {
+                    *path = Some(&line[..n]);
+                    matches(rest, &line[n..], path)
+                })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path<'b>(template: &str, line: &'b str) -> Option<&'b str> {
+        Template::new(template).path(line)
+    }
+
+    #[test]
+    fn template_finds_the_path_of_default_headers() {
+        assert_eq!(path("// {path}", "// src/main.rs"), Some("src/main.rs"));
+        assert_eq!(
+            path("<file path=\"{path}\">", "<file path=\"src/lib.rs\">"),
+            Some("src/lib.rs")
+        );
+        assert_eq!(path("// {path}", "// not a path"), None);
+        assert_eq!(path("// {path}", "/// src/main.rs"), None);
+    }
+
+    #[test]
+    fn template_skips_numbers() {
+        let template = "### {path} ({tokens} tok, {lines} lines)";
+        assert_eq!(
+            path(template, "### src/a.rs (120 tok, 7 lines)"),
+            Some("src/a.rs")
+        );
+        assert_eq!(path(template, "### src/a.rs (many tok, 7 lines)"), None);
+    }
+
+    #[test]
+    fn template_allows_multibyte_literals() {
+        assert_eq!(path("« {path}", "« src/a.rs"), Some("src/a.rs"));
+        assert_eq!(path("{path} → ", "src/a.rs → "), Some("src/a.rs"));
+        assert_eq!(
+            path("<<<FILE {path}>>>", "<<<FILE src/a.rs>>>"),
+            Some("src/a.rs")
+        );
+    }
+
+    #[test]
+    fn parse_reads_back_written_files() {
+        let config = Config::default();
+        for format in [Format::Plain, Format::Markdown, Format::Xml] {
+            let mut dump = Vec::new();
+            let template = config.file_header(format);
+            writeln!(dump, "A question.").unwrap();
+            format
+                .write_file(template, "src/a.rs", "fn a() {}\n", &mut dump)
+                .unwrap();
+            format
+                .write_file(template, "src/b.rs", "fn b() {}\n", &mut dump)
+                .unwrap();
+            let dump = String::from_utf8(dump).unwrap();
+            let paths = parse(&dump, &config)
+                .into_iter()
+                .filter_map(|section| section.path)
+                .collect::<Vec<_>>();
+            assert_eq!(paths, ["src/a.rs", "src/b.rs"]);
+        }
+    }
+
+    #[test]
+    fn xml_round_trips_content_closing_the_element() {
+        let config = Config::default();
+        let template = config.file_header(Format::Xml);
+        let content = "let s = \"\n</file>\n]]>\n<![CDATA[\";\nlet t = \"a]]>b\";\n";
+        let mut dump = Vec::new();
+        Format::Xml
+            .write_file(template, "src/a&<b>.rs", content, &mut dump)
+            .unwrap();
+        Format::Xml
+            .write_file(template, "src/c.rs", "fn c() {}\n", &mut dump)
+            .unwrap();
+        let dump = String::from_utf8(dump).unwrap();
+        let sections = parse(&dump, &config);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].path.as_deref(), Some("src/a&<b>.rs"));
+        assert_eq!(sections[0].content, content);
+        assert_eq!(sections[1].content, "fn c() {}\n");
+        let mut reformatted = Vec::new();
+        for section in &sections {
+            Format::Xml
+                .write_section(&config, section, &mut reformatted)
+                .unwrap();
+        }
+        assert_eq!(String::from_utf8(reformatted).unwrap(), dump);
+    }
+
+    #[test]
+    fn xml_without_cdata_is_still_parsed() {
+        let dump = "<file path=\"src/a.rs\">\nfn a() {}\n</file>\n";
+        let sections = parse(dump, &Config::default());
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].content, "fn a() {}\n");
+    }
+}
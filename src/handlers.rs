@@ -0,0 +1,444 @@
+//! Rendering of data files, lockfiles and images, which would waste tokens
+//! or be unreadable if dumped as they are.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+/// Nesting depth below which JSON and YAML are truncated.
+const DEPTH: usize = 3;
+
+/// Number of items of JSON arrays and rows of CSV files that are kept.
+const ITEMS: usize = 5;
+
+/// Number of lines kept of lockfiles in an unknown format.
+const LOCK_LINES: usize = 20;
+
+/// Renders the files with one of its extensions for the dump.
+pub trait FileHandler: Sync {
+    /// The extensions of the files the handler renders, without the dot.
+    fn extensions(&self) -> &[&str];
+
+    /// Renders a file with content `bytes`.
+    fn render(&self, bytes: &[u8]) -> String;
+}
+
+const HANDLERS: &[&dyn FileHandler] = &[&Json, &Yaml, &Csv, &Lockfile, &Image];
+
+/// Returns the handler of the files with the extension of `path`, if any.
+pub fn find(path: &Path) -> Option<&'static dyn FileHandler> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    HANDLERS
+        .iter()
+        .copied()
+        .find(|handler| handler.extensions().contains(&extension.as_str()))
+}
+
+/// Returns the content of the file at `path` as it is dumped: rendered by its
+/// handler if it has one, or else as text, or `None` if it is not valid UTF-8.
+pub fn render(path: &Path, bytes: &[u8]) -> Option<String> {
+    match find(path) {
+        Some(handler) => Some(handler.render(bytes)),
+        None => String::from_utf8(bytes.to_vec()).ok(),
+    }
+}
+
+/// Truncates nesting deeper than [`DEPTH`] and arrays longer than [`ITEMS`].
+struct Json;
+
+impl FileHandler for Json {
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn render(&self, bytes: &[u8]) -> String {
+        let Ok(value) = serde_json::from_slice::<Value>(bytes) else {
+            return String::from_utf8_lossy(bytes).into_owned();
+        };
+        let mut rendered = String::new();
+        render_json(&value, 0, &mut rendered);
+        rendered.push('\n');
+        rendered
+    }
+}
+
+fn render_json(value: &Value, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth + 1);
+    match value {
+        Value::Array(items) if items.is_empty() => out.push_str("[]"),
+        Value::Object(fields) if fields.is_empty() => out.push_str("{}"),
+        Value::Array(items) if depth >= DEPTH => {
+            out.push_str(&format!("[/* {} items */]", items.len()))
+        }
+        Value::Object(fields) if depth >= DEPTH => {
+            out.push_str(&format!("{{/* {} fields */}}", fields.len()))
+        }
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().take(ITEMS).enumerate() {
+                out.push_str(&indent);
+                render_json(item, depth + 1, out);
+                if i + 1 < items.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            if items.len() > ITEMS {
+                out.push_str(&format!(
+                    "{indent}/* {} more items */\n",
+                    items.len() - ITEMS
+                ));
+            }
+            out.push_str(&"  ".repeat(depth));
+            out.push(']');
+        }
+        Value::Object(fields) => {
+            out.push_str("{\n");
+            for (i, (key, field)) in fields.iter().enumerate() {
+                out.push_str(&format!("{indent}{}: ", Value::String(key.clone())));
+                render_json(field, depth + 1, out);
+                if i + 1 < fields.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            out.push_str(&"  ".repeat(depth));
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// Replaces lines nested deeper than [`DEPTH`] by a comment counting them.
+struct Yaml;
+
+impl FileHandler for Yaml {
+    fn extensions(&self) -> &[&str] {
+        &["yaml", "yml"]
+    }
+
+    fn render(&self, bytes: &[u8]) -> String {
+        let text = String::from_utf8_lossy(bytes);
+        let mut rendered = String::new();
+        // The indentation of the enclosing levels.
+        let mut levels: Vec<usize> = Vec::new();
+        let mut omitted = 0;
+        let mut omitted_indent = 0;
+        for line in text.lines() {
+            let indent = line.len() - line.trim_start().len();
+            let depth = if line.trim().is_empty() {
+                levels.len()
+            } else if line.trim_start().starts_with('#') {
+                // Comments are as deep as the lines they are indented like.
+                levels.iter().filter(|&&level| level < indent).count() + 1
+            } else {
+                while levels.last().is_some_and(|&level| level >= indent) {
+                    levels.pop();
+                }
+                levels.push(indent);
+                levels.len()
+            };
+            if depth > DEPTH {
+                if omitted == 0 {
+                    omitted_indent = indent;
+                }
+                omitted += 1;
+                continue;
+            }
+            if omitted > 0 {
+                let spaces = " ".repeat(omitted_indent);
+                rendered.push_str(&format!("{spaces}# ... {omitted} lines\n"));
+                omitted = 0;
+            }
+            rendered.push_str(line);
+            rendered.push('\n');
+        }
+        if omitted > 0 {
+            let spaces = " ".repeat(omitted_indent);
+            rendered.push_str(&format!("{spaces}# ... {omitted} lines\n"));
+        }
+        rendered
+    }
+}
+
+/// Keeps the header and the first [`ITEMS`] rows.
+struct Csv;
+
+impl FileHandler for Csv {
+    fn extensions(&self) -> &[&str] {
+        &["csv", "tsv"]
+    }
+
+    fn render(&self, bytes: &[u8]) -> String {
+        let text = String::from_utf8_lossy(bytes);
+        let rows = text.lines().count();
+        let mut rendered = String::new();
+        for line in text.lines().take(ITEMS + 1) {
+            rendered.push_str(line);
+            rendered.push('\n');
+        }
+        if rows > ITEMS + 1 {
+            rendered.push_str(&format!("... {} more rows\n", rows - ITEMS - 1));
+        }
+        rendered
+    }
+}
+
+/// Lists the locked packages and versions of lockfiles such as `Cargo.lock`.
+struct Lockfile;
+
+impl FileHandler for Lockfile {
+    fn extensions(&self) -> &[&str] {
+        &["lock"]
+    }
+
+    fn render(&self, bytes: &[u8]) -> String {
+        let text = String::from_utf8_lossy(bytes);
+        let mut packages = Vec::new();
+        let mut name = None;
+        for line in text.lines() {
+            if let Some(value) = line.strip_prefix("name = ") {
+                name = Some(value.trim_matches('"'));
+            } else if let Some(value) = line.strip_prefix("version = ") {
+                if let Some(name) = name.take() {
+                    packages.push(format!("{name} {}", value.trim_matches('"')));
+                }
+            }
+        }
+        if packages.is_empty() {
+            let lines = text.lines().count();
+            let mut rendered = String::new();
+            for line in text.lines().take(LOCK_LINES) {
+                rendered.push_str(line);
+                rendered.push('\n');
+            }
+            if lines > LOCK_LINES {
+                rendered.push_str(&format!("... {} more lines\n", lines - LOCK_LINES));
+            }
+            return rendered;
+        }
+        format!(
+            "{} locked packages:\n{}\n",
+            packages.len(),
+            packages.join("\n")
+        )
+    }
+}
+
+/// Replaces images by their format, dimensions and size.
+struct Image;
+
+impl FileHandler for Image {
+    fn extensions(&self) -> &[&str] {
+        &["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico"]
+    }
+
+    fn render(&self, bytes: &[u8]) -> String {
+        match dimensions(bytes) {
+            Some((format, width, height)) => {
+                format!(
+                    "(a {width}x{height} {format} image of {} bytes)\n",
+                    bytes.len()
+                )
+            }
+            None => format!("(an image of {} bytes)\n", bytes.len()),
+        }
+    }
+}
+
+/// Reads the format and dimensions from the header of a PNG, GIF or JPEG image.
+fn dimensions(bytes: &[u8]) -> Option<(&'static str, u32, u32)> {
+    let be = |i: usize| {
+        Some(u32::from(u16::from_be_bytes([
+            *bytes.get(i)?,
+            *bytes.get(i + 1)?,
+        ])))
+    };
+    if bytes.starts_with(b"\x89PNG") {
+        let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+        return Some(("PNG", width, height));
+    }
+    if bytes.starts_with(b"GIF8") {
+        let width = u16::from_le_bytes(bytes.get(6..8)?.try_into().ok()?);
+        let height = u16::from_le_bytes(bytes.get(8..10)?.try_into().ok()?);
+        return Some(("GIF", width.into(), height.into()));
+    }
+    if bytes.starts_with(b"\xff\xd8") {
+        // The dimensions are in the first start-of-frame segment.
+        let mut i = 2;
+        while i + 9 < bytes.len() && bytes[i] == 0xff {
+            let marker = bytes[i + 1];
+            let length = be(i + 2)? as usize;
+            if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+                return Some(("JPEG", be(i + 7)?, be(i + 5)?));
+            }
+            i += 2 + length;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(file: &str, bytes: &[u8]) -> String {
+        find(Path::new(file)).unwrap().render(bytes)
+    }
+
+    #[test]
+    fn handlers_are_found_by_extension() {
+        assert!(find(Path::new("data/config.JSON")).is_some());
+        assert!(find(Path::new("Cargo.lock")).is_some());
+        assert!(find(Path::new("src/lib.rs")).is_none());
+        assert!(find(Path::new("Makefile")).is_none());
+        assert_eq!(
+            super::render(Path::new("a.txt"), b"\xff\xfe"),
+            None,
+            "text which is not UTF-8 has no rendering"
+        );
+    }
+
+    #[test]
+    fn json_is_truncated_in_depth_and_length() {
+        let json =
+            r#"{"a": {"b": {"c": {"d": 1}, "e": [1, 2]}}, "f": [1, 2, 3, 4, 5, 6, 7], "g": []}"#;
+        assert_eq!(
+            render("a.json", json.as_bytes()),
+            r#"{
+  "a": {
+    "b": {
+      "c": {/* 1 fields */},
+      "e": [/* 2 items */]
+    }
+  },
+  "f": [
+    1,
+    2,
+    3,
+    4,
+    5,
+    /* 2 more items */
+  ],
+  "g": []
+}
+"#
+        );
+    }
+
+    #[test]
+    fn malformed_json_is_kept_as_it_is() {
+        assert_eq!(render("a.json", b"{\"a\": [1, 2"), "{\"a\": [1, 2");
+        assert_eq!(
+            render("a.json", b"{\"a\": \"\xff\"}"),
+            "{\"a\": \"\u{fffd}\"}"
+        );
+    }
+
+    #[test]
+    fn yaml_is_truncated_in_depth() {
+        let yaml = "\
+jobs:
+  test:
+    steps:
+      - run: cargo test
+      - run: cargo clippy
+    # a comment
+    runs-on: ubuntu
+name: ci
+";
+        assert_eq!(
+            render("ci.yml", yaml.as_bytes()),
+            "\
+jobs:
+  test:
+    steps:
+      # ... 2 lines
+    # a comment
+    runs-on: ubuntu
+name: ci
+"
+        );
+        assert_eq!(
+            render("a.yaml", b"a:\n  b:\n    c:\n      d: 1"),
+            "a:\n  b:\n    c:\n      # ... 1 lines\n"
+        );
+    }
+
+    #[test]
+    fn csv_keeps_the_header_and_first_rows() {
+        let csv = (0..10).map(|i| format!("{i},x\n")).collect::<String>();
+        assert_eq!(
+            render("a.csv", format!("n,s\n{csv}").as_bytes()),
+            "n,s\n0,x\n1,x\n2,x\n3,x\n4,x\n... 5 more rows\n"
+        );
+        assert_eq!(render("a.tsv", b"n\ts\n0\tx"), "n\ts\n0\tx\n");
+        assert_eq!(render("a.csv", b""), "");
+    }
+
+    #[test]
+    fn lockfiles_list_their_packages() {
+        let lock = "\
+version = 3
+
+[[package]]
+name = \"serde\"
+version = \"1.0.210\"
+
+[[package]]
+name = \"tokio\"
+version = \"1.38.0\"
+dependencies = [
+ \"pin-project-lite\",
+]
+";
+        assert_eq!(
+            render("Cargo.lock", lock.as_bytes()),
+            "2 locked packages:\nserde 1.0.210\ntokio 1.38.0\n"
+        );
+        let unknown = (0..25).map(|i| format!("line {i}\n")).collect::<String>();
+        let rendered = render("yarn.lock", unknown.as_bytes());
+        assert!(rendered.starts_with("line 0\n"));
+        assert!(rendered.ends_with("line 19\n... 5 more lines\n"));
+    }
+
+    #[test]
+    fn image_dimensions_are_read_from_their_headers() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+        assert_eq!(render("a.png", &png), "(a 640x480 PNG image of 29 bytes)\n");
+
+        let gif = b"GIF89a\x20\x03\x58\x02\0\0\0";
+        assert_eq!(dimensions(gif), Some(("GIF", 800, 600)));
+
+        // SOI, an APP0 segment, and a baseline start-of-frame segment.
+        let mut jpeg = b"\xff\xd8\xff\xe0\x00\x10JFIF\0\x01\x01\0\0\x01\0\x01\0\0".to_vec();
+        jpeg.extend(
+            b"\xff\xc0\x00\x11\x08\x00\x30\x00\x40\x03\x01\x22\x00\x02\x11\x01\x03\x11\x01",
+        );
+        assert_eq!(dimensions(&jpeg), Some(("JPEG", 64, 48)));
+        // A define-Huffman-table segment before the frame is skipped.
+        let mut huffman = b"\xff\xd8\xff\xc4\x00\x04\0\0".to_vec();
+        huffman.extend(&jpeg[2..]);
+        assert_eq!(dimensions(&huffman), Some(("JPEG", 64, 48)));
+    }
+
+    #[test]
+    fn truncated_or_unknown_images_report_their_size() {
+        assert_eq!(dimensions(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0"), None);
+        assert_eq!(dimensions(b"GIF89a\x20"), None);
+        assert_eq!(dimensions(b"\xff\xd8\xff\xe0\x00\x10JFIF"), None);
+        // A segment length pointing past the end.
+        assert_eq!(dimensions(b"\xff\xd8\xff\xe0\xff\xff\0\0\0\0\0\0"), None);
+        assert_eq!(dimensions(b"\xff\xd8"), None);
+        assert_eq!(dimensions(b""), None);
+        assert_eq!(
+            render("a.webp", b"RIFF\0\0\0\0WEBP"),
+            "(an image of 12 bytes)\n"
+        );
+        assert_eq!(render("a.png", b"\x89PNG"), "(an image of 4 bytes)\n");
+    }
+}
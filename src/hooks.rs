@@ -0,0 +1,59 @@
+use std::io::Error;
+use std::io::Write;
+use std::process::Child;
+use std::process::Command;
+use std::process::Stdio;
+
+/// Starts the `post-dump` hook `command`, which reads the dump from a pipe
+/// and writes to standard output.
+pub fn spawn(command: &str) -> Result<Child, Error> {
+    prepare(command)?
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::other(format!("Failed to run the hook `{command}`: {e}")))
+}
+
+/// Waits for the hook `command` to finish and fails if it did.
+pub fn wait(mut child: Child, command: &str) -> Result<(), Error> {
+    if !child.wait()?.success() {
+        return Err(Error::other(format!("The hook `{command}` failed")));
+    }
+    Ok(())
+}
+
+/// Pipes `input` through the hook `command` with `vars` set in its
+/// environment, and returns what it writes.
+pub fn pipe(command: &str, input: &str, vars: &[(&str, &str)]) -> Result<String, Error> {
+    let mut child = prepare(command)?
+        .envs(vars.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::other(format!("Failed to run the hook `{command}`: {e}")))?;
+    let mut stdin = child.stdin.take().expect("Stdin is piped");
+    // Written from another thread, so that a hook writing before it has read
+    // everything cannot block on a full pipe.
+    let output = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output();
+        // A hook may exit without reading all of its input.
+        let _ = writer.join();
+        output
+    })?;
+    if !output.status.success() {
+        return Err(Error::other(format!("The hook `{command}` failed")));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| Error::other(format!("The hook `{command}` wrote invalid UTF-8")))
+}
+
+/// Splits the hook `command` into the program and its arguments, like `$EDITOR`.
+fn prepare(command: &str) -> Result<Command, Error> {
+    let mut words = command.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| Error::other("A hook command is empty"))?;
+    let mut prepared = Command::new(program);
+    prepared.args(words);
+    Ok(prepared)
+}
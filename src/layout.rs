@@ -0,0 +1,341 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::cli::Args;
+use crate::cli::LayoutArgs;
+use crate::config::Config;
+use crate::dump;
+use crate::elide;
+use crate::elide::Item;
+use crate::elide::ItemKind;
+use crate::manifest::Tracked;
+use crate::progress;
+use crate::project::Package;
+use crate::project::Project;
+use crate::toolchain;
+
+pub fn run(
+    args: &LayoutArgs,
+    global: &Args,
+    config: &Config,
+    dir: &Path,
+    project: &Project,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let package = project
+        .package_of(dir)
+        .or(project.packages.first())
+        .ok_or_else(|| Error::other("No package to compute type layouts of"))?;
+
+    // The types defined in the project, by file.
+    let mut definitions = Vec::new();
+    for file in dump::project_files(project, global) {
+        if file.extension().is_some_and(|ext| ext == "rs") {
+            let source = std::fs::read_to_string(&file)?;
+            let types = elide::items(&source)
+                .into_iter()
+                .filter(|item| {
+                    matches!(
+                        item.kind,
+                        ItemKind::Struct | ItemKind::Enum | ItemKind::Union
+                    )
+                })
+                .filter(|item| args.types.is_empty() || args.types.iter().any(|p| item.matches(p)))
+                .collect::<Vec<_>>();
+            if !types.is_empty() {
+                definitions.push((file, source, types));
+            }
+        }
+    }
+
+    // The sizes are only printed when rustc runs, so the crate is rebuilt in
+    // a target directory of its own to not invalidate the regular build.
+    let target_dir = project.target_dir.join("cargo-gpt").join("layout");
+    let sizes = match toolchain::nightly_cargo(dir) {
+        Some(cargo) => print_type_sizes(cargo, args, &package.name, &target_dir, dir)?,
+        None if args.bin.is_none() => {
+            progress::step(
+                "No nightly toolchain for `-Zprint-type-sizes`, computing only sizes and alignments",
+            );
+            probe(package, &definitions, &target_dir)?
+        }
+        None => {
+            return Err(Error::other(
+                "Layouts of binaries need a nightly toolchain for `-Zprint-type-sizes`. \
+                 Install one with `rustup toolchain install nightly`.",
+            ))
+        }
+    };
+    let layouts = layouts(&sizes);
+
+    let selected = |name: &str| {
+        definitions
+            .iter()
+            .any(|(_, _, types)| types.iter().any(|t| t.name == name))
+    };
+    let layouts = layouts
+        .iter()
+        .filter(|(name, _)| selected(base_name(name)))
+        .collect::<Vec<_>>();
+    if layouts.is_empty() {
+        return Err(Error::other(
+            "No layouts were found. Rustc only reports the types which are used \
+             in the code it generates.",
+        ));
+    }
+
+    writeln!(
+        writer,
+        "Below are the memory layouts rustc computed for types of my crate, followed by their \
+         definitions. Review them for wasted padding, enums larger than they need to be, \
+         missed niche optimizations and cache-unfriendly field orders, and suggest changes."
+    )?;
+    for (name, layout) in layouts {
+        writeln!(writer)?;
+        writeln!(writer, "// Layout of `{name}`")?;
+        writer.write_all(layout.as_bytes())?;
+    }
+    writeln!(writer)?;
+    for (file, source, types) in &definitions {
+        let extracted = elide::extract(source, |item| types.iter().any(|t| t.name == item.name));
+        let header = project.header_path(file);
+        let template = config.file_header(global.format);
+        writer.section(&header, file, source.as_bytes(), &extracted, |writer, _| {
+            global
+                .format
+                .write_file(template, &header, &extracted, writer)
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the output of `-Zprint-type-sizes` for the library or `args.bin`
+/// of `package`, compiled by the nightly `cargo` into `target_dir`.
+fn print_type_sizes(
+    mut cargo: Command,
+    args: &LayoutArgs,
+    package: &str,
+    target_dir: &Path,
+    dir: &Path,
+) -> Result<String, Error> {
+    let clean = Command::new("cargo")
+        .args(["clean", "--quiet", "-p", package, "--target-dir"])
+        .arg(target_dir)
+        .current_dir(dir)
+        .output()?;
+    if !clean.status.success() {
+        let stderr = String::from_utf8_lossy(&clean.stderr);
+        return Err(Error::other(format!("`cargo clean` failed:\n{stderr}")));
+    }
+    cargo
+        .args(["rustc", "-p", package, "--target-dir"])
+        .arg(target_dir);
+    match &args.bin {
+        Some(bin) => cargo.args(["--bin", bin]),
+        None => cargo.arg("--lib"),
+    };
+    let output = cargo.args(["--", "-Zprint-type-sizes"]).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::other(format!("`cargo rustc` failed:\n{stderr}")));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Computes the sizes and alignments of the public, non-generic types of
+/// `definitions` on a stable toolchain, by running a crate depending on the
+/// library of `package` which prints them in the format of `-Zprint-type-sizes`.
+fn probe(
+    package: &Package,
+    definitions: &[(PathBuf, String, Vec<Item>)],
+    target_dir: &Path,
+) -> Result<String, Error> {
+    let dir = package
+        .dir()
+        .ok_or_else(|| Error::other(format!("{} has no manifest", package.name)))?;
+    let krate = package.name.replace('-', "_");
+    let mut main = String::from("fn main() {\n");
+    for (file, source, types) in definitions {
+        let Some(module) = module_path(&dir.join("src"), file) else {
+            continue;
+        };
+        for item in types {
+            let generic = source[item.signature.clone()]
+                .split_once(item.name.as_str())
+                .is_some_and(|(_, rest)| rest.trim_start().starts_with('<'));
+            // Inline modules are often private, such as `tests`.
+            if !item.public || generic || !item.scope.is_empty() {
+                continue;
+            }
+            let path = module
+                .iter()
+                .chain([&item.name])
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("::");
+            main.push_str(&format!(
+                "    println!(\"print-type-size type: `{{}}`: {{}} bytes, alignment: {{}} bytes\", \
+                 \"{path}\", std::mem::size_of::<{krate}::{path}>(), std::mem::align_of::<{krate}::{path}>());\n"
+            ));
+        }
+    }
+    main.push_str("}\n");
+
+    let probe = target_dir.join("probe");
+    std::fs::create_dir_all(probe.join("src"))?;
+    let dependency = toml::Value::String(dir.display().to_string());
+    std::fs::write(
+        probe.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"cargo-gpt-layout-probe\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\
+             publish = false\n\n[dependencies]\n{krate} = {{ package = \"{}\", path = {dependency} }}\n\n\
+             [workspace]\n",
+            package.name
+        ),
+    )?;
+    std::fs::write(probe.join("src").join("main.rs"), main)?;
+    let output = Command::new("cargo")
+        .args(["run", "--quiet", "--manifest-path"])
+        .arg(probe.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::other(format!(
+            "Failed to compute the layouts without a nightly toolchain, which only works for \
+             public types in public modules. Install one with `rustup toolchain install nightly` \
+             for all types.\n{stderr}"
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns the path of the module defined by `file` in the library rooted in
+/// `src`, such as `["parser", "token"]` for `src/parser/token.rs`.
+fn module_path(src: &Path, file: &Path) -> Option<Vec<String>> {
+    let relative = file.strip_prefix(src).ok()?.with_extension("");
+    if relative.starts_with("bin") || relative == Path::new("main") {
+        return None;
+    }
+    let mut path = relative
+        .iter()
+        .map(|component| component.to_str().map(str::to_string))
+        .collect::<Option<Vec<_>>>()?;
+    if matches!(path.last().map(String::as_str), Some("lib" | "mod")) {
+        path.pop();
+    }
+    Some(path)
+}
+
+/// Returns the name of a type such as `parser::Token<'_, u8>` without its
+/// module path and generic arguments.
+fn base_name(name: &str) -> &str {
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Splits the output of `-Zprint-type-sizes` into the names of the types
+/// and the descriptions of their layouts.
+fn layouts(sizes: &str) -> Vec<(String, String)> {
+    let mut layouts: Vec<(String, String)> = Vec::new();
+    for line in sizes.lines() {
+        let Some(line) = line.strip_prefix("print-type-size ") else {
+            continue;
+        };
+        if let Some(rest) = line.strip_prefix("type: `") {
+            let Some((name, size)) = rest.split_once("`: ") else {
+                continue;
+            };
+            layouts.push((name.to_string(), format!("{size}\n")));
+        } else if let Some((_, layout)) = layouts.last_mut() {
+            layout.push_str(line);
+            layout.push('\n');
+        }
+    }
+    layouts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of `-Zprint-type-sizes` by rustc 1.95 nightly, shortened.
+    const SIZES: &str = "\
+print-type-size type: `Point`: 16 bytes, alignment: 8 bytes
+print-type-size     field `.y`: 8 bytes
+print-type-size     field `.x`: 1 bytes
+print-type-size     end padding: 7 bytes
+   Compiling demo-lib v0.1.0 (/tmp/demolib)
+print-type-size type: `shapes::Shape`: 8 bytes, alignment: 4 bytes
+print-type-size     discriminant: 1 bytes
+print-type-size     variant `Circle`: 7 bytes
+print-type-size         padding: 3 bytes
+print-type-size         field `.0`: 4 bytes, alignment: 4 bytes
+print-type-size     variant `Square`: 1 bytes
+print-type-size         field `.0`: 1 bytes
+print-type-size type: `std::result::Result<(), std::fmt::Error>`: 1 bytes, alignment: 1 bytes
+";
+
+    #[test]
+    fn layouts_are_split_by_type() {
+        let layouts = layouts(SIZES);
+        let names = layouts
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "Point",
+                "shapes::Shape",
+                "std::result::Result<(), std::fmt::Error>"
+            ]
+        );
+        assert_eq!(
+            layouts[0].1,
+            "16 bytes, alignment: 8 bytes\n    field `.y`: 8 bytes\n    \
+             field `.x`: 1 bytes\n    end padding: 7 bytes\n"
+        );
+        assert!(layouts[1]
+            .1
+            .ends_with("    variant `Square`: 1 bytes\n        field `.0`: 1 bytes\n"));
+        assert_eq!(layouts[2].1, "1 bytes, alignment: 1 bytes\n");
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let sizes = "print-type-size     field `.x`: 1 bytes\n\
+                     print-type-size type: `Broken\n\
+                     print-type-size type: `A`: 1 bytes, alignment: 1 bytes\n";
+        assert_eq!(
+            layouts(sizes),
+            [("A".to_string(), "1 bytes, alignment: 1 bytes\n".to_string())]
+        );
+    }
+
+    #[test]
+    fn names_and_module_paths() {
+        assert_eq!(base_name("parser::Token<'_, u8>"), "Token");
+        assert_eq!(base_name("Point"), "Point");
+        let src = Path::new("/crate/src");
+        assert_eq!(
+            module_path(src, Path::new("/crate/src/parser/token.rs")),
+            Some(vec!["parser".to_string(), "token".to_string()])
+        );
+        assert_eq!(
+            module_path(src, Path::new("/crate/src/parser/mod.rs")),
+            Some(vec!["parser".to_string()])
+        );
+        assert_eq!(
+            module_path(src, Path::new("/crate/src/lib.rs")),
+            Some(vec![])
+        );
+        assert_eq!(module_path(src, Path::new("/crate/src/main.rs")), None);
+        assert_eq!(module_path(src, Path::new("/crate/src/bin/tool.rs")), None);
+        assert_eq!(module_path(src, Path::new("/crate/tests/a.rs")), None);
+    }
+}
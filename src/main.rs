@@ -1,51 +1,290 @@
-use std::fs::File;
 use std::io::BufWriter;
 use std::io::Error;
+use std::io::ErrorKind;
 use std::io::Write;
-use std::path::Path;
+use std::path::PathBuf;
 
-use ignore::WalkBuilder;
+use clap::Parser;
 
-fn main() -> Result<(), Error> {
-    let root = std::env::current_dir()?;
-    let stdout = std::io::stdout();
-    let mut writer = BufWriter::new(stdout);
-    read_dir(&root, &root, &mut writer)
-}
+use anonymize::Anonymizer;
+use budget::Budget;
+use budget::Part;
+use cli::Args;
+use cli::Cargo;
+use cli::Command;
+use cli::ConfigCommand;
+use config::Config;
+use manifest::Tracked;
+use project::Project;
 
-fn read_dir(path: &Path, root: &Path, writer: &mut impl Write) -> Result<(), std::io::Error> {
-    let walk = WalkBuilder::new(path)
-        .filter_entry(|e| {
-            e.file_name()
-                .to_str()
-                .map(|s| !s.starts_with('.') && s != "target")
-                .unwrap_or(false)
-        })
-        .build();
+mod annotate;
+mod anonymize;
+mod api_diff;
+mod asm;
+mod audit;
+mod bloat;
+mod budget;
+mod cli;
+mod config;
+mod contract;
+mod docs;
+mod doctor;
+mod dump;
+mod edit;
+mod elide;
+mod examples;
+mod expand;
+#[cfg(test)]
+mod fixture;
+mod format;
+mod handlers;
+mod hooks;
+mod layout;
+mod manifest;
+mod onboard;
+mod progress;
+mod project;
+mod quick;
+mod reformat;
+mod remote;
+mod share;
+mod stats;
+mod summary;
+mod task;
+mod temp;
+mod tokens;
+mod toolchain;
+mod traits;
+mod transform;
+mod trim;
 
-    for entry in walk.filter_map(Result::ok) {
-        if entry
-            .file_type()
-            .expect("Failed to get file type")
-            .is_file()
-        {
-            let Some(ext) = entry.path().extension() else { continue };
-            let Some(ext) = ext.to_str() else { continue };
-            if ext == "rs" || ext == "md" || entry.file_name() == "Cargo.toml" {
-                read_file(entry.path(), root, writer)?;
+fn main() -> Result<(), Error> {
+    let Cargo::Gpt(args) = Cargo::parse();
+    progress::init(&args);
+    let dir = std::env::current_dir()?;
+    // A broken config should not prevent diagnosing it.
+    let config = match &args.command {
+        Some(Command::Config(_) | Command::Doctor) => Config::default(),
+        _ => Config::load(&dir)?,
+    };
+    let is_paranoid = args.paranoid || config.paranoid();
+    // Checked before the post-dump hook is spawned, which paranoid mode forbids.
+    let allowed = if is_paranoid {
+        Some(paranoid(&args, &config)?)
+    } else {
+        None
+    };
+    let mut hook = match &config.hooks.post_dump {
+        Some(command) => Some(hooks::spawn(command)?),
+        None => None,
+    };
+    let output: Box<dyn Write> = match &mut hook {
+        Some(child) => Box::new(child.stdin.take().expect("Stdin is piped")),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut writer = Tracked::new(BufWriter::new(output));
+    if let Some(dirs) = allowed {
+        writer.restrict(dirs);
+    }
+    match &args.command {
+        Some(Command::ApiDiff(api_diff)) => {
+            let project = Project::load(&dir, &args)?;
+            api_diff::run(api_diff, &args, &dir, &project, &mut writer)
+        }
+        Some(Command::Asm(asm)) => {
+            let project = Project::load(&dir, &args)?;
+            asm::run(asm, &args, &config, &dir, &project, &mut writer)
+        }
+        Some(Command::Audit) => {
+            let project = Project::load(&dir, &args)?;
+            audit::run(&args, &dir, &project, &mut writer)
+        }
+        Some(Command::Config(config_args)) => match config_args.command {
+            ConfigCommand::Check => config::check(&dir, &args, &mut writer),
+        },
+        Some(Command::Deanonymize) => {
+            let project = Project::load(&dir, &args)?;
+            anonymize::restore(&anonymize::mapping_path(&project), &mut writer)
+        }
+        Some(Command::Doctor) => doctor::run(&dir, &mut writer),
+        Some(Command::Bloat(bloat)) => {
+            let project = Project::load(&dir, &args)?;
+            bloat::run(bloat, &args, &config, &dir, &project, &mut writer)
+        }
+        Some(Command::Layout(layout)) => {
+            let project = Project::load(&dir, &args)?;
+            layout::run(layout, &args, &config, &dir, &project, &mut writer)
+        }
+        Some(Command::Onboard(onboard)) => {
+            let project = Project::load(&dir, &args)?;
+            onboard::run(onboard, &args, &config, &project, &mut writer)
+        }
+        Some(Command::Remote(remote)) => remote::run(remote, &args, &config, &mut writer),
+        Some(Command::Share(share)) => {
+            let project = Project::load(&dir, &args)?;
+            share::run(share, &args, &config, &project, is_paranoid, &mut writer)
+        }
+        Some(Command::Stats(stats)) => {
+            let project = Project::load(&dir, &args)?;
+            stats::run(stats, &args, &project, &mut writer)
+        }
+        Some(Command::Summarize) => {
+            let project = Project::load(&dir, &args)?;
+            summary::run(&args, &config, &project, &mut writer)
+        }
+        Some(Command::Transform(transform)) => transform::run(transform, &config, &mut writer),
+        Some(Command::Reformat(reformat)) => {
+            reformat::run(reformat, args.format, &config, &mut writer)
+        }
+        Some(Command::Validate(validate)) => {
+            let project = Project::load(&dir, &args)?;
+            contract::validate(validate, &project.root, &mut writer)
+        }
+        None => {
+            let project = if args.quick {
+                Project::quick(&dir)
+            } else {
+                Project::load(&dir, &args)?
+            };
+            let session = project.root.join(".cargo-gpt").join("session");
+            if args.new_session {
+                match std::fs::remove_file(&session) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            if args.dedupe_session {
+                writer.load_session(&session)?;
             }
+            let mut projects = vec![project];
+            for root in &args.roots {
+                projects.push(Project::load_prefixed(root, &args)?);
+            }
+            // Paranoid mode always anonymizes.
+            if args.anonymize || is_paranoid {
+                let roots = projects
+                    .iter()
+                    .map(|p| p.root.as_path())
+                    .collect::<Vec<_>>();
+                let path = anonymize::mapping_path(&projects[0]);
+                writer.anonymize(Anonymizer::load(path, &roots, &args.anonymize_names)?);
+            }
+            if let Some(budget) = args.budget.or(config.budget) {
+                if args.interactive {
+                    // The files are trimmed to the shares of the code and the attachments.
+                    let [question, code, attachments] = config.budget_split();
+                    let sum = (question + code + attachments).max(1) as usize;
+                    let files = budget * (code + attachments) as usize / sum;
+                    trim::interactive(&mut projects, &args, &config, files)?;
+                }
+                writer.limit(Budget::new(budget, config.budget_split()));
+            } else if args.interactive {
+                return Err(Error::other(
+                    "--interactive requires a budget, given by --budget or `budget` in the config",
+                ));
+            }
+            if let Some(task) = &args.task {
+                let question = task::render(task, &dir, &args.vars)?;
+                let question = writer.fit(Part::Question, question, false, "");
+                writer.write_all(question.as_bytes())?;
+            }
+            if args.edit {
+                let files = projects
+                    .iter()
+                    .flat_map(|project| {
+                        dump::project_files(project, &args)
+                            .into_iter()
+                            .map(|file| project.header_path(&file))
+                    })
+                    .collect::<Vec<_>>();
+                let question = edit::compose(&files)?;
+                let question = writer.fit(Part::Question, question, false, "");
+                writer.write_all(question.as_bytes())?;
+            }
+            if args.answer_anchors {
+                writer.enable_anchors();
+                writeln!(
+                    writer,
+                    "Items in the files below are preceded by anchors such as `// [A1]`. \
+                     When your answer refers to an item, cite its anchor, e.g. [A1].\n"
+                )?;
+            }
+            if args.quick {
+                quick::run(&projects[0], &args, &config, &mut writer)?;
+            } else {
+                for project in &projects {
+                    dump::write_project(project, &args, &config, &mut writer)?;
+                }
+            }
+            if let Some(contract) = args.contract {
+                writeln!(writer)?;
+                writer.write_all(contract::instructions(contract).as_bytes())?;
+            }
+            if args.dedupe_session {
+                writer.save_session(&session)?;
+            }
+            writer.save_anonymizer()?;
+            Ok(())
         }
+    }?;
+    if let Some(path) = &args.manifest {
+        let base = args.reproducible.then_some(dir.as_path());
+        writer.write_manifest(path, base)?;
     }
+    // Closes the input of the hook, so that it can finish.
+    writer.flush()?;
+    drop(writer);
+    if let (Some(child), Some(command)) = (hook, &config.hooks.post_dump) {
+        hooks::wait(child, command)?;
+    }
+    progress::finish();
     Ok(())
 }
 
-fn read_file(path: &Path, root: &Path, writer: &mut impl Write) -> Result<(), std::io::Error> {
-    let mut file = File::open(path)?;
-    let path = path
-        .strip_prefix(root)
-        .expect("Failed to strip prefix")
-        .display();
-    writeln!(writer, "// {path}")?;
-    std::io::copy(&mut file, writer)?;
-    Ok(())
+/// Rejects the commands which reach the network and the hooks, and returns
+/// the allowed directories to restrict the dump to.
+fn paranoid(args: &Args, config: &Config) -> Result<Vec<PathBuf>, Error> {
+    match &args.command {
+        Some(Command::Remote(_)) => {
+            return Err(Error::other(
+                "`remote` fetches from the network, which --paranoid forbids",
+            ))
+        }
+        Some(Command::Audit) => {
+            return Err(Error::other(
+                "`audit` fetches the advisory database from the network, which --paranoid forbids",
+            ))
+        }
+        Some(Command::Share(share)) if share.file.is_none() => {
+            return Err(Error::other(
+                "`share` uploads to GitHub, which --paranoid forbids. Use --file PATH instead.",
+            ))
+        }
+        _ => {}
+    }
+    let hooks = [
+        ("pre-file", &config.hooks.pre_file),
+        ("post-dump", &config.hooks.post_dump),
+    ];
+    if let Some((name, Some(command))) = hooks.iter().find(|(_, command)| command.is_some()) {
+        return Err(Error::other(format!(
+            "`hooks.{name}` in the config runs `{command}`, which could send the dump \
+             anywhere and --paranoid forbids. Remove it to use --paranoid."
+        )));
+    }
+    let dirs = args
+        .allowed_dirs
+        .iter()
+        .chain(config.allowed_dirs())
+        .map(|dir| dump::canonicalize(dir))
+        .collect::<Result<Vec<_>, _>>()?;
+    if dirs.is_empty() {
+        return Err(Error::other(
+            "--paranoid requires the directories to dump from, \
+             given by --allow DIR or `allowed-dirs` in the config",
+        ));
+    }
+    // Keeps cargo from updating the registry index for `cargo metadata` and builds.
+    std::env::set_var("CARGO_NET_OFFLINE", "true");
+    Ok(dirs)
 }
@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::anonymize::Anonymizer;
+use crate::budget::Budget;
+use crate::budget::Part;
+use crate::dump;
+
+/// Describes where a file ended up in the dump, so that answers referring to
+/// the dump can be mapped back to the sources.
+#[derive(Clone, Serialize)]
+pub struct Section {
+    /// The path in the file header.
+    pub header: String,
+    pub source: PathBuf,
+    /// Byte offsets of the section in the dump, including its header.
+    pub bytes: Range<usize>,
+    /// Zero-based line numbers of the section in the dump, including its header.
+    pub lines: Range<usize>,
+    /// Number of lines of the source file.
+    pub source_lines: usize,
+    /// SHA-256 of the source file as it was read.
+    pub sha256: String,
+    /// Whether the content was replaced by a stub since it was already sent
+    /// earlier in the session.
+    pub unchanged: bool,
+    /// The lines of the section in the dump that come from lines of the source.
+    /// Source lines not covered by any chunk were left out.
+    pub chunks: Vec<Chunk>,
+    /// SHA-256 of the content as it was written, which the session records,
+    /// so that content sent trimmed or elided is not taken for the whole file.
+    #[serde(skip)]
+    pub emitted_sha256: String,
+}
+
+/// A run of lines of a section in the dump and the lines of the source file
+/// it was made from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Chunk {
+    /// Zero-based line numbers in the dump.
+    pub lines: Range<usize>,
+    /// Zero-based line numbers in the source file.
+    pub source_lines: Range<usize>,
+    /// Whether the lines were copied as they are, rather than elided or
+    /// otherwise rewritten, such as a function body replaced by a marker.
+    pub copied: bool,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    sections: &'a [Section],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    anchors: &'a [Anchor],
+}
+
+/// An item marked by `--answer-anchors`, so that answers referring to its
+/// anchor can be mapped back to the source.
+#[derive(Serialize)]
+pub struct Anchor {
+    /// The anchor as it appears in the dump, such as `A17`.
+    pub id: String,
+    /// The path in the header of the file containing the item.
+    pub header: String,
+    /// The item qualified by its scope, such as `Parser::parse_expr`.
+    pub item: String,
+    /// One-based line of the item in the source file.
+    pub line: usize,
+}
+
+/// A writer which keeps track of the position in the dump and of the sections
+/// written so far, in this dump and optionally in earlier ones of a session.
+pub struct Tracked<W> {
+    inner: W,
+    bytes: usize,
+    lines: usize,
+    sections: Vec<Section>,
+    /// The content hashes and headers of the sections sent earlier in the session.
+    session: Option<HashSet<String>>,
+    /// The directories sections may come from, if restricted.
+    allowed: Option<Vec<PathBuf>>,
+    anonymizer: Option<Anonymizer>,
+    budget: Option<Budget>,
+    /// The anchors handed out so far, if enabled.
+    anchors: Option<Vec<Anchor>>,
+    /// What was written of the current section.
+    captured: Option<Vec<u8>>,
+}
+
+impl<W: Write> Tracked<W> {
+    pub fn new(inner: W) -> Self {
+        Tracked {
+            inner,
+            bytes: 0,
+            lines: 0,
+            sections: Vec::new(),
+            session: None,
+            allowed: None,
+            anonymizer: None,
+            budget: None,
+            anchors: None,
+            captured: None,
+        }
+    }
+
+    /// Makes [`Tracked::anchor`] hand out anchors.
+    pub fn enable_anchors(&mut self) {
+        self.anchors = Some(Vec::new());
+    }
+
+    /// Returns a new anchor for `item` of the file with `header`, if anchors are enabled.
+    pub fn anchor(&mut self, header: &str, item: String, line: usize) -> Option<String> {
+        let anchors = self.anchors.as_mut()?;
+        let id = format!("A{}", anchors.len() + 1);
+        anchors.push(Anchor {
+            id: id.clone(),
+            header: header.to_string(),
+            item,
+            line,
+        });
+        Some(id)
+    }
+
+    /// Makes [`Tracked::fit`] trim content to `budget`.
+    pub fn limit(&mut self, budget: Budget) {
+        self.budget = Some(budget);
+    }
+
+    /// Returns `content` trimmed to the tokens left to `part`, if there is a budget.
+    pub fn fit(&mut self, part: Part, content: String, is_rust: bool, marker: &str) -> String {
+        match &mut self.budget {
+            Some(budget) => budget.fit(part, content, is_rust, marker),
+            None => content,
+        }
+    }
+
+    /// Gives the tokens of `content`, which was fitted but not written, back to `part`.
+    pub fn refund(&mut self, part: Part, content: &str) {
+        if let Some(budget) = &mut self.budget {
+            budget.refund(part, content);
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Makes [`Tracked::anonymized`] replace identifying strings using `anonymizer`.
+    pub fn anonymize(&mut self, anonymizer: Anonymizer) {
+        self.anonymizer = Some(anonymizer);
+    }
+
+    /// Returns `text` with identifying strings replaced if anonymizing.
+    pub fn anonymized(&mut self, text: &str) -> String {
+        match &mut self.anonymizer {
+            Some(anonymizer) => anonymizer.apply(text),
+            None => text.to_string(),
+        }
+    }
+
+    /// Saves the mapping of the anonymizer, if any.
+    pub fn save_anonymizer(&self) -> Result<(), Error> {
+        match &self.anonymizer {
+            Some(anonymizer) => anonymizer.save(),
+            None => Ok(()),
+        }
+    }
+
+    /// Makes writing a section of a file outside of `dirs` fail.
+    pub fn restrict(&mut self, dirs: Vec<PathBuf>) {
+        self.allowed = Some(dirs);
+    }
+
+    /// Writes a file section using `write`, recording its position in the dump.
+    /// `content` is the source file as it was read, and `emitted` the content
+    /// written for it. `write` is told whether `emitted` was already sent in
+    /// the session.
+    pub fn section(
+        &mut self,
+        header: &str,
+        source: &Path,
+        content: &[u8],
+        emitted: &str,
+        write: impl FnOnce(&mut Self, bool) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if let Some(allowed) = &self.allowed {
+            if !allowed.iter().any(|dir| source.starts_with(dir)) {
+                return Err(Error::other(format!(
+                    "{} is outside of the allowed directories",
+                    source.display()
+                )));
+            }
+        }
+        let (bytes, lines) = (self.bytes, self.lines);
+        let sha256 = format!("{:x}", Sha256::digest(content));
+        let emitted_sha256 = format!("{:x}", Sha256::digest(emitted));
+        let unchanged = self
+            .session
+            .as_ref()
+            .is_some_and(|session| session.contains(&session_entry(&emitted_sha256, header)));
+        self.captured = Some(Vec::new());
+        let written = write(self, unchanged);
+        let captured = self.captured.take().unwrap_or_default();
+        written?;
+        let chunks = if unchanged {
+            Vec::new()
+        } else {
+            let captured = String::from_utf8_lossy(&captured);
+            // Skip the header of the section, if the content is written as it is.
+            let skipped = match captured.find(emitted) {
+                Some(start) if !emitted.is_empty() => captured[..start].matches('\n').count(),
+                _ => 0,
+            };
+            chunks(&String::from_utf8_lossy(content), &captured)
+                .into_iter()
+                .filter(|chunk| chunk.lines.start >= skipped)
+                .map(|chunk| Chunk {
+                    lines: chunk.lines.start + lines..chunk.lines.end + lines,
+                    ..chunk
+                })
+                .collect()
+        };
+        self.sections.push(Section {
+            header: header.to_string(),
+            source: source.to_path_buf(),
+            bytes: bytes..self.bytes,
+            lines: lines..self.lines,
+            source_lines: content.split(|&b| b == b'\n').count(),
+            sha256,
+            unchanged,
+            chunks,
+            emitted_sha256,
+        });
+        Ok(())
+    }
+
+    /// Starts tracking the sections sent in the session recorded at `path`.
+    pub fn load_session(&mut self, path: &Path) -> Result<(), Error> {
+        let session = match std::fs::read_to_string(path) {
+            Ok(text) => text.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e),
+        };
+        self.session = Some(session);
+        Ok(())
+    }
+
+    /// Adds the sections written so far to the session recorded at `path`.
+    pub fn save_session(&self, path: &Path) -> Result<(), Error> {
+        let mut session = self.session.clone().unwrap_or_default();
+        session.extend(
+            self.sections
+                .iter()
+                .map(|section| session_entry(&section.emitted_sha256, &section.header)),
+        );
+        let mut entries = session.into_iter().collect::<Vec<_>>();
+        entries.sort();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, entries.join("\n") + "\n")
+    }
+
+    /// Writes the manifest of the sections to `path`, with their sources
+    /// relative to `base` if given.
+    pub fn write_manifest(&self, path: &Path, base: Option<&Path>) -> Result<(), Error> {
+        let relative = base.map(|base| {
+            self.sections
+                .iter()
+                .map(|section| Section {
+                    source: PathBuf::from(dump::slashed(&dump::relative(&section.source, base))),
+                    ..section.clone()
+                })
+                .collect::<Vec<_>>()
+        });
+        let manifest = Manifest {
+            sections: relative.as_deref().unwrap_or(&self.sections),
+            anchors: self.anchors.as_deref().unwrap_or_default(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&manifest)?)
+    }
+}
+
+fn session_entry(sha256: &str, header: &str) -> String {
+    format!("{sha256} {header}")
+}
+
+/// Maps the lines of `written` to the lines of `source` it was made from.
+/// Equal lines are matched as in a patience diff, and the lines between two
+/// matches in `written` are taken as rewritten from those between them in
+/// `source`.
+fn chunks(source: &str, written: &str) -> Vec<Chunk> {
+    let source = source.lines().collect::<Vec<_>>();
+    let written = written.lines().collect::<Vec<_>>();
+    let mut matched = Vec::new();
+    match_lines(&source, &written, (0, 0), &mut matched);
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (a, b) in matched.into_iter().chain([(source.len(), written.len())]) {
+        if a > i && b > j {
+            chunks.push(Chunk {
+                lines: j..b,
+                source_lines: i..a,
+                copied: false,
+            });
+        }
+        if a == source.len() {
+            break;
+        }
+        match chunks.last_mut() {
+            Some(chunk) if chunk.copied && chunk.lines.end == b && chunk.source_lines.end == a => {
+                chunk.lines.end += 1;
+                chunk.source_lines.end += 1;
+            }
+            _ => chunks.push(Chunk {
+                lines: b..b + 1,
+                source_lines: a..a + 1,
+                copied: true,
+            }),
+        }
+        (i, j) = (a + 1, b + 1);
+    }
+    chunks
+}
+
+/// Appends the pairs of indices of equal lines of `a` and `b`, offset by
+/// `offset`, in increasing order.
+fn match_lines(a: &[&str], b: &[&str], offset: (usize, usize), matched: &mut Vec<(usize, usize)>) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    matched.extend((0..prefix).map(|k| (offset.0 + k, offset.1 + k)));
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (middle_a, middle_b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+    let offset = (offset.0 + prefix, offset.1 + prefix);
+    // Lines occurring exactly once on both sides, as (count in a, index in a,
+    // count in b, index in b).
+    let mut counts: HashMap<&str, (usize, usize, usize, usize)> = HashMap::new();
+    for (k, line) in middle_a.iter().enumerate() {
+        let entry = counts.entry(line).or_default();
+        entry.0 += 1;
+        entry.1 = k;
+    }
+    for (k, line) in middle_b.iter().enumerate() {
+        if let Some(entry) = counts.get_mut(line) {
+            entry.2 += 1;
+            entry.3 = k;
+        }
+    }
+    let mut unique = counts
+        .into_values()
+        .filter(|&(in_a, _, in_b, _)| in_a == 1 && in_b == 1)
+        .map(|(_, k, _, l)| (k, l))
+        .collect::<Vec<_>>();
+    unique.sort();
+    let anchors = increasing(&unique);
+    if !anchors.is_empty() {
+        let (mut k, mut l) = (0, 0);
+        for (next_k, next_l) in anchors {
+            match_lines(
+                &middle_a[k..next_k],
+                &middle_b[l..next_l],
+                (offset.0 + k, offset.1 + l),
+                matched,
+            );
+            matched.push((offset.0 + next_k, offset.1 + next_l));
+            (k, l) = (next_k + 1, next_l + 1);
+        }
+        match_lines(
+            &middle_a[k..],
+            &middle_b[l..],
+            (offset.0 + k, offset.1 + l),
+            matched,
+        );
+    }
+    let (a_end, b_end) = (offset.0 + middle_a.len(), offset.1 + middle_b.len());
+    matched.extend((0..suffix).map(|k| (a_end + k, b_end + k)));
+}
+
+/// Returns the longest subsequence of `pairs`, which are sorted by their
+/// first element, whose second elements are increasing too.
+fn increasing(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // The index of the last pair of the best subsequence of each length, and
+    // the index of the pair preceding each pair in its subsequence.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; pairs.len()];
+    for (k, pair) in pairs.iter().enumerate() {
+        let length = tails.partition_point(|&t| pairs[t].1 < pair.1);
+        previous[k] = length.checked_sub(1).map(|p| tails[p]);
+        match tails.get_mut(length) {
+            Some(tail) => *tail = k,
+            None => tails.push(k),
+        }
+    }
+    let mut subsequence = Vec::new();
+    let mut next = tails.last().copied();
+    while let Some(k) = next {
+        subsequence.push(pairs[k]);
+        next = previous[k];
+    }
+    subsequence.reverse();
+    subsequence
+}
+
+impl<W: Write> Write for Tracked<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let n = self.inner.write(buf)?;
+        if let Some(captured) = &mut self.captured {
+            captured.extend_from_slice(&buf[..n]);
+        }
+        self.bytes += n;
+        self.lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elide;
+    use crate::format::Format;
+
+    /// Writes a section of `source` emitted as `emitted`, returning whether it
+    /// was taken as unchanged.
+    fn write(tracked: &mut Tracked<Vec<u8>>, source: &str, emitted: &str) -> bool {
+        let mut stubbed = false;
+        tracked
+            .section(
+                "src/a.rs",
+                Path::new("src/a.rs"),
+                source.as_bytes(),
+                emitted,
+                |_, unchanged| {
+                    stubbed = unchanged;
+                    Ok(())
+                },
+            )
+            .unwrap();
+        stubbed
+    }
+
+    #[test]
+    fn session_is_keyed_on_the_emitted_content() {
+        let source = "fn a() {\n    1\n}\n";
+        let elided = "fn a() { /* ... */ }\n";
+        let mut first = Tracked::new(Vec::new());
+        first.session = Some(HashSet::new());
+        assert!(!write(&mut first, source, elided));
+        let session = first
+            .sections
+            .iter()
+            .map(|section| session_entry(&section.emitted_sha256, &section.header))
+            .collect();
+
+        let mut second = Tracked::new(Vec::new());
+        second.session = Some(session);
+        assert!(!write(&mut second, source, source));
+        assert!(write(&mut second, source, elided));
+    }
+
+    #[test]
+    fn chunks_map_an_elided_file_to_its_source() {
+        let source = "use std::io;\n\nfn a() {\n    1\n}\n\nfn b() {\n    2\n}\n";
+        let elided = elide::elide_bodies(source, "{ /* ... */ }", |item| item.name == "b");
+        assert_eq!(
+            elided,
+            "use std::io;\n\nfn a() { /* ... */ }\n\nfn b() {\n    2\n}\n"
+        );
+        let mut tracked = Tracked::new(Vec::new());
+        tracked.write_all(b"Files:\n").unwrap();
+        tracked
+            .section(
+                "src/a.rs",
+                Path::new("src/a.rs"),
+                source.as_bytes(),
+                &elided,
+                |writer, _| Format::Plain.write_file("// {path}", "src/a.rs", &elided, writer),
+            )
+            .unwrap();
+        let chunk = |lines: Range<usize>, source_lines: Range<usize>, copied| Chunk {
+            lines,
+            source_lines,
+            copied,
+        };
+        // The dump starts with `Files:` and the header `// src/a.rs`.
+        assert_eq!(
+            tracked.sections[0].chunks,
+            [
+                chunk(2..4, 0..2, true),
+                chunk(4..5, 2..5, false),
+                chunk(5..9, 5..9, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn increasing_finds_the_longest_subsequence() {
+        let pairs = [(0, 3), (1, 0), (2, 4), (3, 1), (4, 2), (5, 5)];
+        assert_eq!(increasing(&pairs), [(1, 0), (3, 1), (4, 2), (5, 5)]);
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::cli::Args;
+use crate::cli::OnboardArgs;
+use crate::config::Config;
+use crate::docs;
+use crate::dump;
+use crate::manifest::Tracked;
+use crate::project::Project;
+
+/// Files which tell how the project is built, tested and checked in CI,
+/// relative to the project root.
+const BUILD_FILES: &[&str] = &[
+    ".gitlab-ci.yml",
+    ".travis.yml",
+    ".circleci/config.yml",
+    "Makefile",
+    "justfile",
+    "Justfile",
+    "Makefile.toml",
+    "CONTRIBUTING.md",
+    "rust-toolchain.toml",
+    "rust-toolchain",
+];
+
+/// Number of the most frequently changed files listed as recent themes.
+const THEMES: usize = 10;
+
+/// Number of the latest commit subjects listed.
+const SUBJECTS: usize = 20;
+
+/// Writes a prompt for briefing a new contributor on the project: a map of
+/// its files, its entry points, how it is built and tested, and what recent
+/// commits have been about.
+pub fn run(
+    args: &OnboardArgs,
+    global: &Args,
+    config: &Config,
+    project: &Project,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    writeln!(
+        writer,
+        "I am a new contributor to the project below. Brief me on the codebase: \
+         what it does, how it is organized, where execution starts, how to build \
+         and test it, and what has been worked on lately. Point out the files I \
+         should read first and the conventions I should follow."
+    )?;
+    let (files, skipped) = dump::walk_project(project, global);
+    dump::write_header(project, &skipped, writer)?;
+
+    writeln!(writer)?;
+    writeln!(writer, "// Map of the repository:")?;
+    for file in &files {
+        let header = project.header_path(file);
+        match describe(file)? {
+            Some(description) => writeln!(writer, "//   {header}: {description}")?,
+            None => writeln!(writer, "//   {header}")?,
+        }
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "// Entry points and manifests:")?;
+    let is_manifest = |file: &PathBuf| {
+        project
+            .packages
+            .iter()
+            .any(|package| package.manifest.as_ref() == Some(file))
+    };
+    for file in files
+        .iter()
+        .filter(|file| is_manifest(file) || is_entry_point(project, file))
+    {
+        dump::read_file(file, project, global, config, writer)?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "// Build, test and CI configuration:")?;
+    for file in build_files(&project.root) {
+        // Written as they are, since the YAML handler would cut the steps of CI jobs.
+        let header = project.header_path(&file);
+        let bytes = std::fs::read(&file)?;
+        let content = String::from_utf8_lossy(&bytes).into_owned();
+        let template = config.file_header(global.format);
+        writer.section(&header, &file, &bytes, &content, |writer, _| {
+            global
+                .format
+                .write_file(template, &header, &content, writer)
+        })?;
+    }
+
+    writeln!(writer)?;
+    write_history(&project.root, args.commits, writer)?;
+    Ok(())
+}
+
+/// Returns the first line of the module documentation of a Rust file, or the
+/// first heading of a markdown file.
+fn describe(file: &Path) -> Result<Option<String>, Error> {
+    let is_rust = file.extension().is_some_and(|ext| ext == "rs");
+    if !is_rust && !docs::is_markdown(file) {
+        return Ok(None);
+    }
+    let source = String::from_utf8_lossy(&std::fs::read(file)?).into_owned();
+    let description = source.lines().map(str::trim).find_map(|line| {
+        let text = if is_rust {
+            line.strip_prefix("//!").map(str::trim)
+        } else {
+            line.strip_prefix('#').map(|line| {
+                let text = line.trim_start_matches('#').trim();
+                // Without the optional closing `#`s, which follow a space.
+                match text.trim_end_matches('#') {
+                    heading if heading.ends_with(' ') => heading.trim_end(),
+                    _ => text,
+                }
+            })
+        };
+        text.filter(|text| !text.is_empty())
+    });
+    Ok(description.map(str::to_string))
+}
+
+/// Returns true if `file` is where a crate of the project starts: the root of
+/// a library or binary target, or a build script.
+fn is_entry_point(project: &Project, file: &Path) -> bool {
+    project
+        .packages
+        .iter()
+        .any(|package| package.entry_points.iter().any(|entry| entry == file))
+}
+
+/// Returns the build and CI files of the project at `root`, including the
+/// GitHub Actions workflows.
+fn build_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = BUILD_FILES
+        .iter()
+        .map(|file| root.join(file))
+        .filter(|file| file.is_file())
+        .collect::<Vec<_>>();
+    if let Ok(entries) = std::fs::read_dir(root.join(".github/workflows")) {
+        let mut workflows = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "yml" || ext == "yaml")
+            })
+            .collect::<Vec<_>>();
+        workflows.sort();
+        files.extend(workflows);
+    }
+    files
+}
+
+/// Writes the files changed most often in the last `commits` commits and the
+/// subjects of the latest ones. Nothing is written outside of a git repository.
+fn write_history(root: &Path, commits: usize, writer: &mut impl Write) -> Result<(), Error> {
+    let output = Command::new("git")
+        .args([
+            "log",
+            &format!("-{commits}"),
+            "--name-only",
+            "--format=%x1e%s",
+        ])
+        .current_dir(root)
+        .output();
+    let Some(output) = output.ok().filter(|output| output.status.success()) else {
+        writeln!(writer, "// The git history is not available.")?;
+        return Ok(());
+    };
+    let log = String::from_utf8_lossy(&output.stdout);
+    let mut subjects = Vec::new();
+    let mut changes: BTreeMap<&str, usize> = BTreeMap::new();
+    for commit in log.split('\x1e').filter(|commit| !commit.trim().is_empty()) {
+        let mut lines = commit.lines();
+        subjects.push(lines.next().unwrap_or(""));
+        for file in lines.filter(|line| !line.is_empty()) {
+            *changes.entry(file).or_default() += 1;
+        }
+    }
+    let mut changes = changes.into_iter().collect::<Vec<_>>();
+    changes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    writeln!(
+        writer,
+        "// Files changed most often in the last {} commits:",
+        subjects.len()
+    )?;
+    for (file, count) in changes.into_iter().take(THEMES) {
+        writeln!(writer, "//   {file} ({count})")?;
+    }
+    writeln!(writer, "// Latest commits:")?;
+    for subject in subjects.into_iter().take(SUBJECTS) {
+        writeln!(writer, "//   {subject}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn files_are_described_by_their_docs_or_heading() {
+        let fixture = Fixture::new();
+        let lib = fixture.write(
+            "src/lib.rs",
+            "\n//!\n//! Parses tokens.\n//! More.\nfn a() {}\n",
+        );
+        let plain = fixture.write("src/a.rs", "// Not a module doc.\nfn a() {}\n");
+        let readme = fixture.write("README.md", "\n## The project ##\n\nText.\n");
+        let manifest = fixture.write("Cargo.toml", "# A comment\n");
+        assert_eq!(describe(&lib).unwrap().as_deref(), Some("Parses tokens."));
+        assert_eq!(describe(&plain).unwrap(), None);
+        assert_eq!(describe(&readme).unwrap().as_deref(), Some("The project"));
+        assert_eq!(describe(&manifest).unwrap(), None);
+        let csharp = fixture.write("docs/csharp.md", "# Bindings for C#\n");
+        assert_eq!(
+            describe(&csharp).unwrap().as_deref(),
+            Some("Bindings for C#")
+        );
+    }
+
+    #[test]
+    fn entry_points_are_the_roots_of_targets() {
+        let fixture = Fixture::new();
+        let lib = fixture.write("src/lib.rs", "");
+        let tool = fixture.write("src/bin/tool.rs", "");
+        let server = fixture.write("src/bin/server/main.rs", "");
+        let build = fixture.write("build.rs", "");
+        let nested = fixture.write("src/parser/main.rs", "");
+        let module = fixture.write("src/bin/server/util.rs", "");
+        let project = Project::quick(&fixture.dir);
+        for file in [&lib, &tool, &server, &build] {
+            assert!(is_entry_point(&project, file), "{}", file.display());
+        }
+        for file in [&nested, &module] {
+            assert!(!is_entry_point(&project, file), "{}", file.display());
+        }
+    }
+
+    #[test]
+    fn history_lists_frequent_files_and_subjects() {
+        let fixture = Fixture::new();
+        let mut history = Vec::new();
+        write_history(&fixture.dir, 10, &mut history).unwrap();
+        assert_eq!(
+            String::from_utf8(history).unwrap(),
+            "// The git history is not available.\n"
+        );
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=a", "-c", "user.email=a@example.com"])
+                .args(args)
+                .current_dir(&fixture.dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        git(&["init", "--quiet"]);
+        for (subject, files) in [
+            ("Add a", &["a.rs"][..]),
+            ("Add b", &["a.rs", "b.rs"]),
+            ("Change a", &["a.rs"]),
+        ] {
+            for file in files {
+                let path = fixture.dir.join(file);
+                let previous = std::fs::read_to_string(&path).unwrap_or_default();
+                std::fs::write(&path, previous + subject + "\n").unwrap();
+            }
+            git(&["add", "."]);
+            git(&["commit", "--quiet", "-m", subject]);
+        }
+        let mut history = Vec::new();
+        write_history(&fixture.dir, 2, &mut history).unwrap();
+        assert_eq!(
+            String::from_utf8(history).unwrap(),
+            "\
+// Files changed most often in the last 2 commits:
+//   a.rs (2)
+//   b.rs (1)
+// Latest commits:
+//   Change a
+//   Add b
+"
+        );
+    }
+}
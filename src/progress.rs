@@ -0,0 +1,138 @@
+use std::io::IsTerminal;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde_json::json;
+
+use crate::cli::Args;
+
+/// How often the progress bar is redrawn at most.
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// Progress is reported from wherever work happens, so it is kept globally
+/// rather than threaded through every function.
+static STATE: Mutex<State> = Mutex::new(State {
+    verbosity: 0,
+    json: false,
+    bar: false,
+    drawn: None,
+    roots: Vec::new(),
+    walked: 0,
+    files: 0,
+    tokens: 0,
+});
+
+struct State {
+    /// Negative for `-q`, positive for each `-v`.
+    verbosity: i8,
+    /// Whether events are logged as JSON lines instead of text.
+    json: bool,
+    /// Whether a progress bar is shown.
+    bar: bool,
+    /// When the progress bar was last drawn.
+    drawn: Option<Instant>,
+    /// The directories walked so far, since the files of a project are
+    /// listed again by the steps which need them.
+    roots: Vec<PathBuf>,
+    walked: usize,
+    files: usize,
+    tokens: usize,
+}
+
+impl State {
+    /// Prints a line, above the progress bar if one is shown.
+    fn print(&mut self, line: &str) {
+        if self.drawn.take().is_some() {
+            eprint!("\r\x1b[2K");
+        }
+        eprintln!("{line}");
+    }
+
+    fn draw(&mut self) {
+        if !self.bar || self.drawn.is_some_and(|drawn| drawn.elapsed() < REDRAW) {
+            return;
+        }
+        eprint!(
+            "\r\x1b[2KWalked {} files, read {}, ~{} tokens",
+            self.walked, self.files, self.tokens
+        );
+        self.drawn = Some(Instant::now());
+    }
+}
+
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn init(args: &Args) {
+    let mut state = state();
+    // `-v` may be repeated more often than an `i8` counts.
+    let verbose = i8::try_from(args.verbose).unwrap_or(i8::MAX);
+    state.verbosity = if args.quiet { -1 } else { verbose };
+    state.json = args.log_json;
+    state.bar = state.verbosity >= 0 && !args.log_json && std::io::stderr().is_terminal();
+}
+
+/// Returns true if `-q` was given, so that the output of other tools should be
+/// suppressed as well.
+pub fn quiet() -> bool {
+    state().verbosity < 0
+}
+
+/// Reports the start of a potentially slow step, such as running `cargo metadata`.
+pub fn step(message: &str) {
+    let mut state = state();
+    if state.json {
+        state.print(&json!({ "event": "step", "message": message }).to_string());
+    } else if state.verbosity >= 1 {
+        state.print(message);
+    }
+}
+
+/// Reports the start of a walk of `root`, returning true if its files should
+/// be reported with [`walked`], which is only the first time it is walked.
+pub fn walking(root: &Path) -> bool {
+    let mut state = state();
+    if state.roots.iter().any(|walked| walked == root) {
+        return false;
+    }
+    state.roots.push(root.to_path_buf());
+    true
+}
+
+/// Reports that `count` more files were found while walking the project.
+pub fn walked(count: usize) {
+    let mut state = state();
+    state.walked += count;
+    state.draw();
+}
+
+/// Reports that the file with `header` was written with about `tokens` tokens.
+pub fn file(header: &str, tokens: usize) {
+    let mut state = state();
+    state.files += 1;
+    state.tokens += tokens;
+    if state.json {
+        state.print(&json!({ "event": "file", "path": header, "tokens": tokens }).to_string());
+    } else if state.verbosity >= 2 {
+        state.print(&format!("{header}: ~{tokens} tokens"));
+    }
+    state.draw();
+}
+
+/// Clears the progress bar and reports the totals.
+pub fn finish() {
+    let mut state = state();
+    let (files, tokens) = (state.files, state.tokens);
+    if state.json {
+        state.print(&json!({ "event": "done", "files": files, "tokens": tokens }).to_string());
+    } else if state.verbosity >= 1 {
+        state.print(&format!("Wrote {files} files, ~{tokens} tokens"));
+    } else if state.drawn.take().is_some() {
+        eprint!("\r\x1b[2K");
+    }
+}
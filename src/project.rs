@@ -0,0 +1,603 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::io::Error;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use cargo_metadata::semver::Version;
+use cargo_metadata::Message;
+use cargo_metadata::MetadataCommand;
+use serde::Deserialize;
+
+use crate::cli::Args;
+use crate::dump;
+use crate::elide;
+use crate::expand;
+use crate::expand::Expansion;
+use crate::progress;
+use crate::stats;
+
+/// The packages whose sources make up a dump.
+pub struct Project {
+    /// Directory that file headers are relative to.
+    pub root: PathBuf,
+    /// Prepended to file headers to tell projects of a merged dump apart.
+    pub prefix: PathBuf,
+    /// Directory of build artifacts, which is never walked.
+    pub target_dir: PathBuf,
+    pub packages: Vec<Package>,
+    /// Manifests which do not belong to a package of the dump: the workspace
+    /// root's and those of path dependencies, included by `--toml`.
+    pub manifests: Vec<PathBuf>,
+    /// Checked out git submodules inside the root, included by `--submodules`.
+    pub submodules: Vec<PathBuf>,
+    /// The files and paths of the functions whose bodies are elided, by
+    /// `--elide-largest` or when trimming interactively.
+    pub elided: Vec<(PathBuf, String)>,
+    /// Files left out when trimming interactively.
+    pub excluded: Vec<PathBuf>,
+}
+
+pub struct Package {
+    pub name: String,
+    pub manifest: Option<PathBuf>,
+    pub readme: Option<PathBuf>,
+    /// Names of the crates compiled from the package's targets.
+    pub crates: Vec<String>,
+    /// Directories containing the sources of the package's targets.
+    pub source_dirs: Vec<PathBuf>,
+    /// Sources living outside of `source_dirs`, such as build scripts.
+    pub source_files: Vec<PathBuf>,
+    /// The root files of the library, binaries and build script.
+    pub entry_points: Vec<PathBuf>,
+    /// Directory of code generated by the package's build script.
+    pub out_dir: Option<PathBuf>,
+    /// The `[features]` table of the manifest.
+    pub features: BTreeMap<String, Vec<String>>,
+    /// Dependencies by the crate name they are used under in code, mapped to
+    /// the package and version they resolve to, such as `tokio 1.38`.
+    pub dependencies: BTreeMap<String, String>,
+    /// Directories of the library sources of dependencies by the crate name
+    /// they are used under, known with `--foreign-traits`.
+    pub sources: BTreeMap<String, PathBuf>,
+    /// The edition, for packages without a manifest which would tell it.
+    pub edition: Option<String>,
+    /// The code generated by macros for the items selected by `--expand-derives`.
+    pub expansions: BTreeMap<String, Expansion>,
+}
+
+impl Project {
+    /// Discovers the project containing `dir` through `cargo metadata`, or
+    /// through a `rust-project.json` outside of cargo projects. Other
+    /// directories are treated as a single package.
+    pub fn load(dir: &Path, args: &Args) -> Result<Project, Error> {
+        let mut project = Project::discover(dir, args)?;
+        if let Some(n) = args.elide_largest {
+            project.elided = stats::functions(&project, args)?
+                .into_iter()
+                .take(n)
+                .map(|function| (function.file, function.path))
+                .collect();
+        }
+        Ok(project)
+    }
+
+    /// Reads the workspace containing `dir` without running `cargo metadata`,
+    /// for `--quick`. The workspace is found by looking for a `[workspace]`
+    /// table in the manifests above `dir`, and its members by matching the
+    /// patterns of its `members` against the directories.
+    pub fn quick(dir: &Path) -> Project {
+        let manifests = dir
+            .ancestors()
+            .filter(|d| d.join("Cargo.toml").is_file())
+            .collect::<Vec<_>>();
+        let workspace = manifests.iter().rev().find(|d| {
+            std::fs::read_to_string(d.join("Cargo.toml"))
+                .is_ok_and(|manifest| manifest.lines().any(|l| l.trim() == "[workspace]"))
+        });
+        let root = workspace.or(manifests.first()).copied().unwrap_or(dir);
+        let mut project = Project::plain(root);
+        // Members are pruned from the walk of the root, since they have a manifest.
+        project
+            .packages
+            .extend(members(root).iter().map(|member| plain_package(member)));
+        project
+    }
+
+    fn discover(dir: &Path, args: &Args) -> Result<Project, Error> {
+        if !dir.ancestors().any(|d| d.join("Cargo.toml").is_file()) {
+            return match dir
+                .ancestors()
+                .map(|d| d.join("rust-project.json"))
+                .find(|path| path.is_file())
+            {
+                Some(path) => Project::rust_project(&path),
+                None => Ok(Project::plain(dir)),
+            };
+        }
+        progress::step(&format!("Running `cargo metadata` in {}", dir.display()));
+        let mut command = MetadataCommand::new();
+        command.current_dir(dir);
+        if !args.path_deps && !args.foreign_traits {
+            command.no_deps();
+        }
+        let metadata = command.exec().map_err(Error::other)?;
+
+        let mut out_dirs = if args.out_dir {
+            out_dirs(dir)?
+        } else {
+            HashMap::new()
+        };
+
+        let locked = locked_versions(&metadata.workspace_root.clone().into_std_path_buf());
+        let packages = metadata
+            .packages
+            .iter()
+            .filter(|p| {
+                metadata.workspace_members.contains(&p.id) || (args.path_deps && p.source.is_none())
+            })
+            .map(|p| -> Result<Package, Error> {
+                let manifest = p.manifest_path.clone().into_std_path_buf();
+                let package_dir = manifest
+                    .parent()
+                    .expect("Manifest has a parent")
+                    .to_path_buf();
+                let readme = match &p.readme {
+                    Some(readme) => Some(package_dir.join(readme)),
+                    None => Some(package_dir.join("README.md")).filter(|r| r.is_file()),
+                };
+                let mut source_dirs = Vec::new();
+                let mut source_files = Vec::new();
+                let mut entry_points = Vec::new();
+                for target in &p.targets {
+                    let src_path = target.src_path.clone().into_std_path_buf();
+                    let starts = target.kind.iter().any(|k| {
+                        k.contains("lib")
+                            || matches!(k.as_str(), "bin" | "proc-macro" | "custom-build")
+                    });
+                    if starts {
+                        entry_points.push(src_path.clone());
+                    }
+                    if target.kind.iter().any(|k| k == "custom-build") {
+                        source_files.push(src_path);
+                    } else if let Some(dir) = src_path.parent() {
+                        source_dirs.push(dir.to_path_buf());
+                    }
+                }
+                // Macros are expanded in the library, or else in the first binary.
+                let target = if p.targets.iter().any(|t| {
+                    t.kind
+                        .iter()
+                        .any(|k| k.contains("lib") || k == "proc-macro")
+                }) {
+                    Some(vec!["--lib"])
+                } else {
+                    p.targets
+                        .iter()
+                        .find(|t| t.kind.iter().any(|k| k == "bin"))
+                        .map(|t| vec!["--bin", t.name.as_str()])
+                };
+                let expansions = match target {
+                    Some(target) if !args.expand_derives.is_empty() => {
+                        expand::expand(&manifest, &target, &args.expand_derives)?
+                    }
+                    _ => BTreeMap::new(),
+                };
+                Ok(Package {
+                    name: p.name.clone(),
+                    manifest: Some(manifest),
+                    readme,
+                    crates: p.targets.iter().map(|t| t.name.replace('-', "_")).collect(),
+                    source_dirs: outermost(source_dirs),
+                    source_files,
+                    entry_points,
+                    out_dir: out_dirs.remove(&p.id.repr),
+                    features: p.features.clone().into_iter().collect(),
+                    dependencies: p
+                        .dependencies
+                        .iter()
+                        .map(|dep| {
+                            let krate = dep.rename.as_ref().unwrap_or(&dep.name).replace('-', "_");
+                            let version = locked
+                                .iter()
+                                .filter(|(name, version)| {
+                                    *name == dep.name && dep.req.matches(version)
+                                })
+                                .map(|(_, version)| version)
+                                .max();
+                            let origin = match version {
+                                Some(v) => format!("{} {}.{}", dep.name, v.major, v.minor),
+                                None => dep.name.clone(),
+                            };
+                            (krate, origin)
+                        })
+                        .collect(),
+                    sources: p
+                        .dependencies
+                        .iter()
+                        .filter_map(|dep| {
+                            let krate = dep.rename.as_ref().unwrap_or(&dep.name).replace('-', "_");
+                            let lib = metadata
+                                .packages
+                                .iter()
+                                .filter(|q| q.name == dep.name && dep.req.matches(&q.version))
+                                .max_by_key(|q| &q.version)?
+                                .targets
+                                .iter()
+                                .find(|t| t.kind.iter().any(|k| k.contains("lib")))?;
+                            let dir = lib.src_path.parent()?.to_path_buf().into_std_path_buf();
+                            Some((krate, dir))
+                        })
+                        .collect(),
+                    edition: None,
+                    expansions,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let root = metadata.workspace_root.into_std_path_buf();
+        let mut manifests = vec![root.join("Cargo.toml")];
+        manifests.extend(
+            metadata
+                .packages
+                .iter()
+                .filter(|p| metadata.workspace_members.contains(&p.id))
+                .flat_map(|p| &p.dependencies)
+                .filter_map(|dep| dep.path.as_ref())
+                .map(|path| path.join("Cargo.toml").into_std_path_buf()),
+        );
+        manifests.sort();
+        manifests.dedup();
+        Ok(Project {
+            submodules: submodules(&root),
+            root,
+            prefix: PathBuf::new(),
+            target_dir: metadata.target_directory.into_std_path_buf(),
+            packages,
+            manifests,
+            elided: Vec::new(),
+            excluded: Vec::new(),
+        })
+    }
+
+    fn plain(dir: &Path) -> Project {
+        Project {
+            root: dir.to_path_buf(),
+            prefix: PathBuf::new(),
+            target_dir: dir.join("target"),
+            packages: vec![plain_package(dir)],
+            manifests: Vec::new(),
+            submodules: submodules(dir),
+            elided: Vec::new(),
+            excluded: Vec::new(),
+        }
+    }
+
+    /// Reads the crates of a project built by another build system, such as
+    /// Bazel or Buck, from the `rust-project.json` it generated for rust-analyzer.
+    fn rust_project(path: &Path) -> Result<Project, Error> {
+        let root = path.parent().expect("File has a parent").to_path_buf();
+        let text = std::fs::read_to_string(path)?;
+        let json: RustProject = serde_json::from_str(&text)
+            .map_err(|e| Error::other(format!("{}: {e}", path.display())))?;
+        let name = |krate: &RustCrate| {
+            krate.display_name.clone().unwrap_or_else(|| {
+                let stem = krate.root_module.file_stem().unwrap_or_default();
+                stem.to_string_lossy().into_owned()
+            })
+        };
+        let packages = json
+            .crates
+            .iter()
+            .filter(|krate| krate.is_workspace_member.unwrap_or(true))
+            .map(|krate| {
+                let root_module = root.join(&krate.root_module);
+                let source_dirs = match &krate.source {
+                    Some(source) if !source.include_dirs.is_empty() => source
+                        .include_dirs
+                        .iter()
+                        .map(|dir| root.join(dir))
+                        .collect(),
+                    _ => root_module
+                        .parent()
+                        .map(Path::to_path_buf)
+                        .into_iter()
+                        .collect(),
+                };
+                Package {
+                    name: name(krate),
+                    manifest: None,
+                    readme: None,
+                    crates: vec![name(krate).replace('-', "_")],
+                    source_dirs: outermost(source_dirs),
+                    source_files: Vec::new(),
+                    entry_points: vec![root_module],
+                    out_dir: None,
+                    features: BTreeMap::new(),
+                    dependencies: krate
+                        .deps
+                        .iter()
+                        .filter_map(|dep| {
+                            let target = json.crates.get(dep.krate)?;
+                            Some((dep.name.replace('-', "_"), name(target)))
+                        })
+                        .collect(),
+                    sources: krate
+                        .deps
+                        .iter()
+                        .filter_map(|dep| {
+                            let target = json.crates.get(dep.krate)?;
+                            let dir = root.join(&target.root_module).parent()?.to_path_buf();
+                            Some((dep.name.replace('-', "_"), dir))
+                        })
+                        .collect(),
+                    edition: Some(krate.edition.clone()),
+                    expansions: BTreeMap::new(),
+                }
+            })
+            .collect();
+        Ok(Project {
+            target_dir: root.join("target"),
+            submodules: submodules(&root),
+            root,
+            prefix: PathBuf::new(),
+            packages,
+            manifests: Vec::new(),
+            elided: Vec::new(),
+            excluded: Vec::new(),
+        })
+    }
+
+    /// Loads an additional project whose file headers are prefixed by its name.
+    pub fn load_prefixed(dir: &Path, args: &Args) -> Result<Project, Error> {
+        let mut project = Project::load(&dump::canonicalize(dir)?, args)?;
+        project.prefix = project
+            .root
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        Ok(project)
+    }
+
+    /// Returns the path of `file` as shown in its header, separated by `/` on
+    /// all platforms so that dumps do not depend on the machine.
+    pub fn header_path(&self, file: &Path) -> String {
+        dump::slashed(&self.prefix.join(dump::relative(file, &self.root)))
+    }
+
+    /// Returns the package whose directory contains `file`.
+    pub fn package_of(&self, file: &Path) -> Option<&Package> {
+        self.packages
+            .iter()
+            .filter(|p| p.dir().is_some_and(|dir| file.starts_with(dir)))
+            .max_by_key(|p| p.dir().map(|dir| dir.components().count()))
+    }
+
+    /// Names of all crates compiled from the project.
+    pub fn crates(&self) -> impl Iterator<Item = &str> {
+        self.packages
+            .iter()
+            .flat_map(|p| p.crates.iter().map(String::as_str))
+    }
+}
+
+/// Returns a package of all files in `dir`, named after the directory, with
+/// the entry points Cargo would find in it by convention.
+fn plain_package(dir: &Path) -> Package {
+    let mut entry_points = ["src/lib.rs", "src/main.rs", "build.rs"]
+        .iter()
+        .map(|file| dir.join(file))
+        .filter(|file| file.is_file())
+        .collect::<Vec<_>>();
+    if let Ok(entries) = std::fs::read_dir(dir.join("src").join("bin")) {
+        let mut bins = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter_map(|path| match path.is_dir() {
+                true => Some(path.join("main.rs")).filter(|main| main.is_file()),
+                false => Some(path).filter(|path| path.extension().is_some_and(|ext| ext == "rs")),
+            })
+            .collect::<Vec<_>>();
+        bins.sort();
+        entry_points.extend(bins);
+    }
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Package {
+        name,
+        manifest: None,
+        readme: None,
+        crates: Vec::new(),
+        source_dirs: vec![dir.to_path_buf()],
+        source_files: Vec::new(),
+        entry_points,
+        out_dir: None,
+        features: BTreeMap::new(),
+        dependencies: BTreeMap::new(),
+        sources: BTreeMap::new(),
+        edition: None,
+        expansions: BTreeMap::new(),
+    }
+}
+
+/// Returns the directories of the members of the workspace at `root` which
+/// have a manifest, where `*` in the patterns of `members` and `exclude`
+/// matches any part of a directory name.
+fn members(root: &Path) -> Vec<PathBuf> {
+    let Ok(manifest) = std::fs::read_to_string(root.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let Ok(manifest) = manifest.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let patterns = |key| {
+        manifest
+            .get("workspace")
+            .and_then(|workspace| workspace.get(key))
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .collect::<Vec<_>>()
+    };
+    let exclude = patterns("exclude");
+    let mut members = Vec::new();
+    for pattern in patterns("members") {
+        let mut dirs = vec![root.to_path_buf()];
+        for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+            dirs = dirs
+                .iter()
+                .flat_map(|dir| {
+                    if !component.contains('*') {
+                        return vec![dir.join(component)];
+                    }
+                    std::fs::read_dir(dir)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Result::ok)
+                        .map(|entry| entry.path())
+                        .filter(|path| {
+                            path.is_dir()
+                                && path
+                                    .file_name()
+                                    .and_then(|name| name.to_str())
+                                    .is_some_and(|name| elide::glob(component, name))
+                        })
+                        .collect()
+                })
+                .collect();
+        }
+        members.extend(dirs.into_iter().filter(|dir| {
+            let relative = dump::slashed(&dump::relative(dir, root));
+            dir != root
+                && dir.join("Cargo.toml").is_file()
+                && !exclude
+                    .iter()
+                    .any(|pattern| elide::glob(pattern, &relative))
+        }));
+    }
+    members.sort();
+    members.dedup();
+    members
+}
+
+impl Package {
+    /// Returns the directory of the package's manifest.
+    pub fn dir(&self) -> Option<&Path> {
+        self.manifest.as_deref().and_then(Path::parent)
+    }
+}
+
+/// The subset of rust-analyzer's `rust-project.json` that we use.
+#[derive(Deserialize)]
+struct RustProject {
+    crates: Vec<RustCrate>,
+}
+
+#[derive(Deserialize)]
+struct RustCrate {
+    display_name: Option<String>,
+    /// Relative to the directory of `rust-project.json`, like all paths.
+    root_module: PathBuf,
+    edition: String,
+    #[serde(default)]
+    deps: Vec<RustDep>,
+    is_workspace_member: Option<bool>,
+    source: Option<RustSource>,
+}
+
+#[derive(Deserialize)]
+struct RustDep {
+    /// Index of the crate in `crates`.
+    #[serde(rename = "crate")]
+    krate: usize,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct RustSource {
+    include_dirs: Vec<PathBuf>,
+}
+
+/// Returns the checked out git submodules inside `root`, as listed in the
+/// `.gitmodules` of the repository containing it.
+fn submodules(root: &Path) -> Vec<PathBuf> {
+    let Some(repo) = root.ancestors().find(|dir| dir.join(".git").exists()) else {
+        return Vec::new();
+    };
+    let Ok(gitmodules) = std::fs::read_to_string(repo.join(".gitmodules")) else {
+        return Vec::new();
+    };
+    let mut submodules = gitmodules
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "path").then(|| repo.join(value.trim()))
+        })
+        .filter(|dir| dir.starts_with(root) && dir.join(".git").exists())
+        .collect::<Vec<_>>();
+    submodules.sort();
+    submodules
+}
+
+/// Reads the resolved versions of all packages from the `Cargo.lock` in `root`.
+fn locked_versions(root: &Path) -> Vec<(String, Version)> {
+    let Ok(lock) = std::fs::read_to_string(root.join("Cargo.lock")) else {
+        return Vec::new();
+    };
+    let mut versions = Vec::new();
+    let mut name = None;
+    for line in lock.lines() {
+        if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"').to_string());
+        } else if let Some(value) = line.strip_prefix("version = ") {
+            if let (Some(name), Ok(version)) =
+                (name.take(), Version::parse(value.trim_matches('"')))
+            {
+                versions.push((name, version));
+            }
+        }
+    }
+    versions
+}
+
+/// Runs `cargo check` to find the `OUT_DIR` of every package with a build script.
+fn out_dirs(dir: &Path) -> Result<HashMap<String, PathBuf>, Error> {
+    progress::step("Running `cargo check` to find generated code");
+    let mut command = Command::new("cargo");
+    command
+        .args(["check", "--message-format=json"])
+        .current_dir(dir)
+        .stdout(Stdio::piped());
+    if progress::quiet() {
+        command.stderr(Stdio::null());
+    }
+    let mut child = command.spawn()?;
+    let stdout = child.stdout.take().expect("Stdout is piped");
+    let mut out_dirs = HashMap::new();
+    for message in Message::parse_stream(BufReader::new(stdout)) {
+        if let Message::BuildScriptExecuted(script) = message? {
+            out_dirs.insert(script.package_id.repr, script.out_dir.into_std_path_buf());
+        }
+    }
+    child.wait()?;
+    Ok(out_dirs)
+}
+
+/// Removes directories nested inside other directories of the list.
+fn outermost(mut dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    dirs.sort();
+    dirs.dedup();
+    let mut result: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if !result.iter().any(|outer| dir.starts_with(outer)) {
+            result.push(dir);
+        }
+    }
+    result
+}
@@ -0,0 +1,110 @@
+use std::io::Error;
+use std::io::Write;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::dump;
+use crate::manifest::Tracked;
+use crate::project::Project;
+
+/// Names of the files included by `--quick`: manifests, crate roots and module roots.
+const STRUCTURAL: &[&str] = &["Cargo.toml", "lib.rs", "main.rs", "mod.rs"];
+
+/// Writes a tree of the files of `project` and its workspace members followed
+/// by their manifests, crate roots and module roots, without running cargo,
+/// for an overview of huge repositories.
+pub fn run(
+    project: &Project,
+    args: &Args,
+    config: &Config,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let mut files = project
+        .packages
+        .iter()
+        .flat_map(|package| &package.source_dirs)
+        .flat_map(|dir| dump::source_files(dir, &project.target_dir, args))
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    let headers = files
+        .iter()
+        .map(|file| writer.anonymized(&project.header_path(file)))
+        .collect::<Vec<_>>();
+    writeln!(writer, "// Files of the project:")?;
+    write_tree(&headers, writer)?;
+    writeln!(writer)?;
+    for file in &files {
+        let structural = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| STRUCTURAL.contains(&name));
+        if structural {
+            dump::read_file(file, project, args, config, writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `paths` as an indented tree, with each directory on its own line.
+/// The paths are sorted, so that the files of a directory are adjacent.
+fn write_tree(paths: &[String], writer: &mut impl Write) -> Result<(), Error> {
+    let mut sorted = paths.iter().map(String::as_str).collect::<Vec<_>>();
+    sorted.sort_unstable();
+    let mut previous: Vec<&str> = Vec::new();
+    for path in sorted {
+        let components = path.split('/').collect::<Vec<_>>();
+        let (name, dirs) = components.split_last().expect("Paths are not empty");
+        let common = previous
+            .iter()
+            .zip(dirs)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, dir) in dirs.iter().enumerate().skip(common) {
+            writeln!(writer, "// {}{dir}/", "  ".repeat(depth))?;
+        }
+        writeln!(writer, "// {}{name}", "  ".repeat(dirs.len()))?;
+        previous = dirs.to_vec();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anonymize::Anonymizer;
+    use crate::fixture;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn anonymized_quick_dumps_contain_no_names() {
+        let fixture = Fixture::new();
+        fixture.write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/acme-core\"]\n",
+        );
+        fixture.write(
+            "crates/acme-core/Cargo.toml",
+            "[package]\nname = \"acme-core\"\n",
+        );
+        fixture.write(
+            "crates/acme-core/src/lib.rs",
+            "pub use acme_core::Engine;\n",
+        );
+        fixture.write("crates/acme-core/src/engine.rs", "pub struct Engine;\n");
+        let project = Project::quick(&fixture.dir);
+        let args = fixture::args(&["--quick", "--anonymize", "--anonymize-names", "acme-core"]);
+        let path = fixture.dir.join("anonymize.json");
+        let roots = [fixture.dir.as_path()];
+        let anonymizer = Anonymizer::load(path, &roots, &args.anonymize_names).unwrap();
+        let mut writer = Tracked::new(Vec::new());
+        writer.anonymize(anonymizer);
+        run(&project, &args, &Config::default(), &mut writer).unwrap();
+        let dump = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(dump.contains("engine.rs"), "{dump}");
+        assert!(dump.contains("pub use"), "{dump}");
+        assert!(!dump.to_lowercase().contains("acme"), "{dump}");
+        let dir = fixture.dir.to_string_lossy();
+        assert!(!dump.contains(dir.as_ref()), "{dump}");
+    }
+}
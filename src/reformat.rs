@@ -0,0 +1,147 @@
+use std::io::Error;
+use std::io::Write;
+
+use crate::cli::ReformatArgs;
+use crate::config::Config;
+use crate::format;
+use crate::format::Format;
+use crate::format::Section;
+use crate::tokens;
+
+pub fn run(
+    args: &ReformatArgs,
+    format: Format,
+    config: &Config,
+    writer: &mut impl Write,
+) -> Result<(), Error> {
+    let dump = std::fs::read_to_string(&args.input)?;
+    let mut sections = format::parse(&dump, config);
+    let chunks = match args.chunk_tokens {
+        Some(limit) => {
+            // The text before the first file, such as the project header, is
+            // repeated in every part so that each can be used on its own.
+            let preamble = match sections.first() {
+                Some(section) if section.path.is_none() => Some(sections.remove(0)),
+                _ => None,
+            };
+            let chunks = chunk(&sections, limit);
+            let total = chunks.len();
+            chunks
+                .into_iter()
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let mut part = Vec::new();
+                    if total > 1 {
+                        writeln!(part, "// Part {} of {total}", i + 1)?;
+                    }
+                    if let Some(preamble) = &preamble {
+                        format.write_section(config, preamble, &mut part)?;
+                    }
+                    for section in chunk {
+                        format.write_section(config, section, &mut part)?;
+                    }
+                    Ok(part)
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        }
+        None => {
+            let mut all = Vec::new();
+            for section in &sections {
+                format.write_section(config, section, &mut all)?;
+            }
+            vec![all]
+        }
+    };
+    match &args.out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            let extension = match format {
+                Format::Plain => "txt",
+                Format::Markdown => "md",
+                Format::Xml => "xml",
+            };
+            for (i, part) in chunks.iter().enumerate() {
+                let path = dir.join(format!("part-{}.{extension}", i + 1));
+                std::fs::write(&path, part)?;
+                writeln!(writer, "{}", path.display())?;
+            }
+        }
+        None => {
+            for part in &chunks {
+                writer.write_all(part)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Packs sections in order into chunks of at most `limit` tokens. A section
+/// exceeding the limit on its own gets a chunk of its own.
+fn chunk(sections: &[Section], limit: usize) -> Vec<Vec<&Section>> {
+    let mut chunks: Vec<Vec<&Section>> = Vec::new();
+    let mut size = 0;
+    for section in sections {
+        let tokens = tokens::estimate(&section.content);
+        match chunks.last_mut() {
+            Some(chunk) if size + tokens <= limit => {
+                chunk.push(section);
+                size += tokens;
+            }
+            _ => {
+                chunks.push(vec![section]);
+                size = tokens;
+            }
+        }
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn chunks_are_written_to_separate_files_with_the_preamble() {
+        let fixture = Fixture::new();
+        let config = Config::default();
+        let mut dump = b"The project.\n".to_vec();
+        for name in ["a", "b", "c"] {
+            let content = format!("fn {name}() {{ {} }}\n", "x".repeat(40));
+            Format::Xml
+                .write_file(
+                    config.file_header(Format::Xml),
+                    &format!("src/{name}.rs"),
+                    &content,
+                    &mut dump,
+                )
+                .unwrap();
+        }
+        let input = fixture.write("dump.xml", &dump);
+        let out_dir = fixture.dir.join("parts");
+        let args = ReformatArgs {
+            input,
+            chunk_tokens: Some(25),
+            out_dir: Some(out_dir.clone()),
+        };
+        let mut written = Vec::new();
+        run(&args, Format::Markdown, &config, &mut written).unwrap();
+        assert_eq!(
+            written
+                .split(|&b| b == b'\n')
+                .filter(|l| !l.is_empty())
+                .count(),
+            3
+        );
+        for (i, name) in ["a", "b", "c"].iter().enumerate() {
+            let part = std::fs::read_to_string(out_dir.join(format!("part-{}.md", i + 1))).unwrap();
+            let sections = format::parse(&part, &config);
+            assert!(part.starts_with(&format!("// Part {} of 3\nThe project.\n", i + 1)));
+            let paths = sections
+                .iter()
+                .filter_map(|s| s.path.as_deref())
+                .collect::<Vec<_>>();
+            assert_eq!(paths, [format!("src/{name}.rs")]);
+        }
+    }
+}
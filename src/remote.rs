@@ -0,0 +1,52 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::cli::Args;
+use crate::cli::RemoteArgs;
+use crate::config::Config;
+use crate::dump;
+use crate::manifest::Tracked;
+use crate::project::Project;
+use crate::temp;
+
+/// A temporary checkout which is removed when dropped.
+pub struct Checkout(pub PathBuf);
+
+impl Checkout {
+    /// Shallow-clones `rev` of the repository at `url`, which may also be a local path.
+    pub fn fetch(url: &str, rev: &str) -> Result<Checkout, Error> {
+        let checkout = Checkout(temp::create_dir("cargo-gpt-remote")?);
+        git(&["init", "--quiet"], &checkout.0)?;
+        git(&["fetch", "--quiet", "--depth", "1", url, rev], &checkout.0)?;
+        git(&["checkout", "--quiet", "FETCH_HEAD"], &checkout.0)?;
+        Ok(checkout)
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn run(
+    args: &RemoteArgs,
+    global: &Args,
+    config: &Config,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let checkout = Checkout::fetch(&args.url, args.rev.as_deref().unwrap_or("HEAD"))?;
+    let project = Project::load(&checkout.0, global)?;
+    dump::write_project(&project, global, config, writer)
+}
+
+fn git(args: &[&str], dir: &Path) -> Result<(), Error> {
+    let status = Command::new("git").args(args).current_dir(dir).status()?;
+    if !status.success() {
+        return Err(Error::other(format!("`git {}` failed", args.join(" "))));
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::process::Stdio;
+
+use crate::anonymize;
+use crate::anonymize::Anonymizer;
+use crate::cli::Args;
+use crate::cli::ShareArgs;
+use crate::config::Config;
+use crate::doctor;
+use crate::dump;
+use crate::manifest::Tracked;
+use crate::project::Project;
+
+/// Uploads an anonymized dump of the project as a secret GitHub gist, or
+/// writes it to a local file, and prints its URL and copies it if a
+/// clipboard tool is available and not `paranoid`.
+pub fn run(
+    args: &ShareArgs,
+    global: &Args,
+    config: &Config,
+    project: &Project,
+    paranoid: bool,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    // The dump is always anonymized, since it leaves the machine.
+    let mut dump = Tracked::new(Vec::new());
+    let mapping = anonymize::mapping_path(project);
+    dump.anonymize(Anonymizer::load(
+        mapping,
+        &[&project.root],
+        &global.anonymize_names,
+    )?);
+    dump::write_project(project, global, config, &mut dump)?;
+    dump.save_anonymizer()?;
+    let dump = dump.into_inner();
+
+    let url = match &args.file {
+        Some(path) => {
+            std::fs::write(path, &dump)?;
+            file_url(&dump::canonicalize(path)?)
+        }
+        None => gist(&dump, &args.description)?,
+    };
+    writeln!(writer, "{url}")?;
+    // Other users may read the clipboard of a shared machine.
+    if !paranoid {
+        copy(&url);
+    }
+    Ok(())
+}
+
+/// Returns the `file:` URL of the absolute `path`, such as `file:///tmp/a.md`,
+/// `file:///C:/a.md` or `file://server/share/a.md`.
+fn file_url(path: &Path) -> String {
+    let path = dump::slashed(path);
+    // The server of a UNC path is the host of the URL.
+    let path = match path.strip_prefix("//") {
+        Some(unc) => unc.to_string(),
+        None if path.starts_with('/') => path,
+        None => format!("/{path}"),
+    };
+    let mut url = String::from("file://");
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/:-._~".contains(&byte) {
+            url.push(char::from(byte));
+        } else {
+            url.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    url
+}
+
+/// Creates a secret gist with `content` through the GitHub CLI and returns its URL.
+fn gist(content: &[u8], description: &str) -> Result<String, Error> {
+    let mut child = Command::new("gh")
+        .args([
+            "gist",
+            "create",
+            "--filename",
+            "prompt.md",
+            "--desc",
+            description,
+            "-",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            Error::other(format!(
+                "Failed to run `gh`, which uploads the gist: {e}. \
+                 Install the GitHub CLI or pass --file PATH."
+            ))
+        })?;
+    child
+        .stdin
+        .take()
+        .expect("Stdin is piped")
+        .write_all(content)?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::other("`gh gist create` failed"));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let url = stdout
+        .lines()
+        .rev()
+        .find(|line| line.starts_with("https://"))
+        .ok_or_else(|| Error::other("`gh gist create` printed no URL"))?;
+    Ok(url.to_string())
+}
+
+/// Copies `text` with the first clipboard tool found, if any.
+fn copy(text: &str) {
+    let Some((tool, args, _)) = doctor::CLIPBOARD_TOOLS
+        .iter()
+        .find(|(tool, _, _)| doctor::find(tool).is_some())
+    else {
+        return;
+    };
+    let child = Command::new(tool)
+        .args(*args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn();
+    if let Ok(mut child) = child {
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        let _ = child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_urls_have_an_empty_host() {
+        assert_eq!(file_url(Path::new("/tmp/dump.md")), "file:///tmp/dump.md");
+        assert_eq!(
+            file_url(Path::new("/tmp/my dump#1.md")),
+            "file:///tmp/my%20dump%231.md"
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn file_urls_of_windows_paths() {
+        assert_eq!(
+            file_url(Path::new(r"C:\tmp\dump.md")),
+            "file:///C:/tmp/dump.md"
+        );
+        assert_eq!(
+            file_url(Path::new(r"\\server\share\dump.md")),
+            "file://server/share/dump.md"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::cli::Args;
+use crate::cli::StatsArgs;
+use crate::dump;
+use crate::elide;
+use crate::elide::ItemKind;
+use crate::handlers;
+use crate::project::Project;
+use crate::tokens;
+
+/// The size of a function with a body.
+pub struct Function {
+    pub file: PathBuf,
+    /// The name qualified by its scope, such as `Parser::parse_expr`.
+    pub path: String,
+    pub lines: usize,
+    pub tokens: usize,
+    pub complexity: usize,
+}
+
+pub fn run(
+    args: &StatsArgs,
+    global: &Args,
+    project: &Project,
+    writer: &mut impl Write,
+) -> Result<(), Error> {
+    if args.functions {
+        writeln!(
+            writer,
+            "{:>7} {:>6} {:>10}  function",
+            "tokens", "lines", "complexity"
+        )?;
+        for function in functions(project, global)?.iter().take(args.count) {
+            writeln!(
+                writer,
+                "{:>7} {:>6} {:>10}  {}: {}",
+                function.tokens,
+                function.lines,
+                function.complexity,
+                project.header_path(&function.file),
+                function.path
+            )?;
+        }
+        return Ok(());
+    }
+    // Sized as they are dumped, such as data files rendered by their handlers.
+    let mut files = Vec::new();
+    let mut binary = 0;
+    for file in dump::project_files(project, global) {
+        let bytes = std::fs::read(&file)?;
+        match handlers::render(&file, &bytes) {
+            Some(content) => {
+                files.push((tokens::estimate(&content), content.lines().count(), file))
+            }
+            None => binary += 1,
+        }
+    }
+    files.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+    let total = files.iter().map(|(tokens, _, _)| tokens).sum::<usize>();
+    writeln!(writer, "{:>7} {:>6}  file", "tokens", "lines")?;
+    for (tokens, lines, file) in files.iter().take(args.count) {
+        writeln!(
+            writer,
+            "{tokens:>7} {lines:>6}  {}",
+            project.header_path(file)
+        )?;
+    }
+    writeln!(writer, "{total:>7} {:>6}  in {} files", "", files.len())?;
+    if binary > 0 {
+        writeln!(
+            writer,
+            "{:>14}  skipped {binary} files which are not UTF-8",
+            ""
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the functions with a body in the Rust files of the project, the
+/// largest first.
+pub fn functions(project: &Project, args: &Args) -> Result<Vec<Function>, Error> {
+    let mut functions = Vec::new();
+    for file in dump::project_files(project, args) {
+        if file.extension().is_some_and(|ext| ext == "rs") {
+            let bytes = std::fs::read(&file)?;
+            let Ok(source) = String::from_utf8(bytes) else {
+                continue;
+            };
+            for item in elide::items(&source) {
+                let Some(body) = &item.body else { continue };
+                if item.kind != ItemKind::Fn {
+                    continue;
+                }
+                let text = &source[item.range.clone()];
+                functions.push(Function {
+                    file: file.clone(),
+                    path: item.path(),
+                    lines: text.lines().count(),
+                    tokens: tokens::estimate(text),
+                    complexity: elide::complexity(&source[body.clone()]),
+                });
+            }
+        }
+    }
+    functions.sort_by(|a, b| {
+        b.tokens
+            .cmp(&a.tokens)
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn files_are_sized_as_rendered_and_binary_ones_skipped() {
+        let fixture = Fixture::new();
+        fixture.write("src/lib.rs", "pub fn a() {}\n");
+        fixture.write("src/latin1.rs", b"// caf\xe9\nfn b() {}\n");
+        let rows = (0..1000).map(|i| format!("{i},row\n")).collect::<String>();
+        fixture.write("src/rows.csv", format!("n,name\n{rows}"));
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x08".to_vec();
+        png.extend([0xff; 4000]);
+        fixture.write("src/logo.png", png);
+        let project = Project::quick(&fixture.dir);
+        let args = StatsArgs {
+            functions: false,
+            count: 20,
+        };
+        let mut report = Vec::new();
+        run(&args, &fixture::args(&["--data"]), &project, &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        let size = |name: &str| -> usize {
+            let line = report.lines().find(|line| line.ends_with(name)).unwrap();
+            line.split_whitespace().next().unwrap().parse().unwrap()
+        };
+        assert!(size("src/rows.csv") < 50, "{report}");
+        assert!(size("src/logo.png") < 50, "{report}");
+        assert!(!report.contains("latin1"), "{report}");
+        assert!(
+            report.contains("skipped 1 files which are not UTF-8"),
+            "{report}"
+        );
+
+        let functions = functions(&project, &fixture::args(&[])).unwrap();
+        let paths = functions
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["a"]);
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::dump;
+use crate::elide;
+use crate::elide::ItemKind;
+use crate::manifest::Tracked;
+use crate::project::Project;
+
+/// Starts the first line of a cached summary, followed by the hash of the source.
+const HASH_PREFIX: &str = "// sha256: ";
+
+/// Refreshes the cached summaries of all Rust files and writes them in place
+/// of the files.
+pub fn run(
+    args: &Args,
+    config: &Config,
+    project: &Project,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    let (files, skipped) = dump::walk_project(project, args);
+    dump::write_header(project, &skipped, writer)?;
+    for file in files {
+        if file.extension().is_some_and(|ext| ext == "rs") {
+            let bytes = std::fs::read(&file)?;
+            let summary = cached(project, &file, &bytes)?;
+            let header = project.header_path(&file);
+            let template = config.file_header(args.format);
+            writer.section(&header, &file, &bytes, &summary, |writer, _| {
+                args.format.write_file(template, &header, &summary, writer)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the summary of `file` with content `bytes`, from the cache in
+/// `.cargo-gpt/summaries/` unless the file changed since it was cached.
+pub fn cached(project: &Project, file: &Path, bytes: &[u8]) -> Result<String, Error> {
+    let hash = format!("{HASH_PREFIX}{:x}", Sha256::digest(bytes));
+    let path = cache_path(project, file);
+    if let Ok(cache) = std::fs::read_to_string(&path) {
+        if let Some((first, summary)) = cache.split_once('\n') {
+            if first == hash {
+                return Ok(summary.to_string());
+            }
+        }
+    }
+    let summary = summarize(&String::from_utf8_lossy(bytes));
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, format!("{hash}\n{summary}"))?;
+    Ok(summary)
+}
+
+/// Returns the path of the cached summary of `file`, named by the hash of its
+/// header path, which may contain `..` for files outside of the project.
+fn cache_path(project: &Project, file: &Path) -> PathBuf {
+    let name = format!("{:x}", Sha256::digest(project.header_path(file)));
+    project
+        .root
+        .join(".cargo-gpt")
+        .join("summaries")
+        .join(format!("{name}.txt"))
+}
+
+/// Summarizes a module by the first paragraph of its documentation and the
+/// signatures of its public items.
+pub fn summarize(source: &str) -> String {
+    let mut summary = String::new();
+    let docs = source
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty() || *line == "//!")
+        .take_while(|line| line.starts_with("//!") && *line != "//!");
+    for line in docs {
+        summary.push_str(line);
+        summary.push('\n');
+    }
+    let items = elide::items(source);
+    let private = items.iter().filter(|item| !item.public).count();
+    for item in items.iter().filter(|item| item.public) {
+        let signature = elide::normalize(&source[item.signature.clone()]);
+        let signature = match item.kind {
+            ItemKind::Fn => format!("{};", signature.trim_end_matches(';').trim_end()),
+            // The fields, variants and methods are left out.
+            _ => match signature.find('{') {
+                Some(open) => format!("{}{{ .. }}", &signature[..open]),
+                None => signature,
+            },
+        };
+        let scope = item.scope.join("::");
+        if scope.is_empty() {
+            summary.push_str(&format!("{signature}\n"));
+        } else {
+            summary.push_str(&format!("/* in {scope} */ {signature}\n"));
+        }
+    }
+    if private > 0 {
+        summary.push_str(&format!("// and {private} private items\n"));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    const SOURCE: &str = "\
+//! Parses tokens.
+//! Into trees.
+//!
+//! More details.
+
+use std::io::Error;
+
+/// A parser.
+pub struct Parser<'a> {
+    tokens: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: &'a [u8]) -> Self {
+        Parser { tokens }
+    }
+
+    fn peek(&self) -> u8 {
+        self.tokens[0]
+    }
+}
+
+pub fn parse(
+    source: &str,
+) -> Result<(), Error> {
+    Ok(())
+}
+
+pub trait Visit {
+    fn visit(&self);
+}
+
+fn helper() {}
+";
+
+    #[test]
+    fn summary_has_the_docs_and_public_signatures() {
+        assert_eq!(
+            summarize(SOURCE),
+            "\
+//! Parses tokens.
+//! Into trees.
+pub struct Parser<'a> { .. }
+/* in Parser */ pub fn new(tokens: &'a [u8]) -> Self;
+pub fn parse( source: &str, ) -> Result<(), Error>;
+pub trait Visit { .. }
+// and 3 private items
+"
+        );
+    }
+
+    #[test]
+    fn summary_of_empty_source_is_empty() {
+        assert_eq!(summarize(""), "");
+        assert_eq!(summarize("fn main() {}\n"), "// and 1 private items\n");
+    }
+
+    #[test]
+    fn cached_summaries_are_refreshed_when_the_source_changes() {
+        let fixture = Fixture::new();
+        let file = fixture.write("src/lib.rs", "pub fn a() {}\n");
+        let project = Project::quick(&fixture.dir);
+        let first = cached(&project, &file, b"pub fn a() {}\n").unwrap();
+        assert_eq!(first, "pub fn a();\n");
+        let cache = cache_path(&project, &file);
+        assert!(cache.starts_with(fixture.dir.join(".cargo-gpt").join("summaries")));
+        // A stale cache for the same content is used as it is.
+        let hash = std::fs::read_to_string(&cache).unwrap();
+        let hash = hash.lines().next().unwrap();
+        std::fs::write(&cache, format!("{hash}\ncached\n")).unwrap();
+        assert_eq!(
+            cached(&project, &file, b"pub fn a() {}\n").unwrap(),
+            "cached\n"
+        );
+        assert_eq!(
+            cached(&project, &file, b"pub fn b() {}\n").unwrap(),
+            "pub fn b();\n"
+        );
+    }
+}
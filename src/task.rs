@@ -0,0 +1,172 @@
+use std::io::BufRead;
+use std::io::Error;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Renders the prompt of the task `spec`, such as `custom:migrate-async`, for
+/// which a template is looked up in `.cargo-gpt/tasks/` of the project
+/// containing `dir` and of the global config directory. Placeholders such as
+/// `{{from}}` are filled from `vars` or else asked for on the terminal.
+pub fn render(spec: &str, dir: &Path, vars: &[String]) -> Result<String, Error> {
+    let Some(name) = spec.strip_prefix("custom:") else {
+        return Err(Error::other(format!(
+            "Unknown task `{spec}`, expected `custom:NAME` for a template \
+             in `.cargo-gpt/tasks/NAME.md`"
+        )));
+    };
+    let path = template_path(dir, name).ok_or_else(|| {
+        Error::other(format!(
+            "No template `{name}.md` in `.cargo-gpt/tasks/` of the project \
+             or the config directory"
+        ))
+    })?;
+    let template = std::fs::read_to_string(&path)?;
+
+    let mut values = Vec::new();
+    for var in vars {
+        let (key, value) = var
+            .split_once('=')
+            .ok_or_else(|| Error::other(format!("Expected `KEY=VALUE`, found `{var}`")))?;
+        values.push((key.trim().to_string(), value.to_string()));
+    }
+    fill(&template, values, ask)
+}
+
+/// Replaces the placeholders of `template` by their `values`, asking for
+/// missing ones with `ask` once each.
+fn fill(
+    template: &str,
+    mut values: Vec<(String, String)>,
+    mut ask: impl FnMut(&str) -> Result<String, Error>,
+) -> Result<String, Error> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let key = rest[start + 2..start + 2 + len].trim();
+        rendered.push_str(&rest[..start]);
+        let value = match values.iter().find(|(k, _)| k == key) {
+            Some((_, value)) => value.clone(),
+            None => {
+                let value = ask(key)?;
+                values.push((key.to_string(), value.clone()));
+                value
+            }
+        };
+        rendered.push_str(&value);
+        rest = &rest[start + 2 + len + 2..];
+    }
+    rendered.push_str(rest);
+    if !rendered.ends_with('\n') {
+        rendered.push('\n');
+    }
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+fn template_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let file = format!("{name}.md");
+    dir.ancestors()
+        .map(|d| d.join(".cargo-gpt").join("tasks").join(&file))
+        .chain(dirs::config_dir().map(|d| d.join("cargo-gpt").join("tasks").join(&file)))
+        .find(|path| path.is_file())
+}
+
+/// Asks for the value of the placeholder `key` on the terminal.
+fn ask(key: &str) -> Result<String, Error> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Err(Error::other(format!(
+            "No value for `{{{{{key}}}}}`, pass it with `--set {key}=VALUE`"
+        )));
+    }
+    eprint!("{key}: ");
+    std::io::stderr().flush()?;
+    let mut value = String::new();
+    stdin.lock().read_line(&mut value)?;
+    Ok(value.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn never(key: &str) -> Result<String, Error> {
+        panic!("asked for {key}")
+    }
+
+    #[test]
+    fn templates_are_found_and_filled() {
+        let fixture = Fixture::new();
+        fixture.write(
+            ".cargo-gpt/tasks/migrate.md",
+            "Migrate from {{from}} to {{ to }}.",
+        );
+        let dir = fixture.dir.join("src").join("nested");
+        std::fs::create_dir_all(&dir).unwrap();
+        let vars = ["from=tokio".to_string(), " to =async-std".to_string()];
+        assert_eq!(
+            render("custom:migrate", &dir, &vars).unwrap(),
+            "Migrate from tokio to async-std.\n\n"
+        );
+    }
+
+    #[test]
+    fn unknown_tasks_and_malformed_values_are_errors() {
+        let fixture = Fixture::new();
+        fixture.write(".cargo-gpt/tasks/a.md", "A");
+        let error = render("migrate", &fixture.dir, &[]).unwrap_err();
+        assert!(error.to_string().contains("Unknown task `migrate`"));
+        let error = render("custom:cargo-gpt-missing", &fixture.dir, &[]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("No template `cargo-gpt-missing.md`"));
+        let error = render("custom:a", &fixture.dir, &["from".to_string()]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Expected `KEY=VALUE`, found `from`"));
+    }
+
+    #[test]
+    fn values_may_contain_equal_signs() {
+        let fixture = Fixture::new();
+        fixture.write(".cargo-gpt/tasks/a.md", "Set {{flag}}.\n");
+        let vars = ["flag=RUSTFLAGS=-Dwarnings".to_string()];
+        assert_eq!(
+            render("custom:a", &fixture.dir, &vars).unwrap(),
+            "Set RUSTFLAGS=-Dwarnings.\n\n"
+        );
+    }
+
+    #[test]
+    fn missing_placeholders_are_asked_for_once() {
+        let mut asked = Vec::new();
+        let rendered = fill("{{a}} {{b}} {{a}} {{ b }}", Vec::new(), |key| {
+            asked.push(key.to_string());
+            Ok(key.to_uppercase())
+        })
+        .unwrap();
+        assert_eq!(rendered, "A B A B\n\n");
+        assert_eq!(asked, ["a", "b"]);
+    }
+
+    #[test]
+    fn unanswered_placeholders_are_errors() {
+        let error = fill("{{a}}", Vec::new(), |key| {
+            Err(Error::other(format!("No value for {key}")))
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "No value for a");
+    }
+
+    #[test]
+    fn unclosed_placeholders_are_left_as_they_are() {
+        let values = vec![("a".to_string(), "1".to_string())];
+        assert_eq!(fill("{{a}} {{b", values, never).unwrap(), "1 {{b\n\n");
+    }
+}
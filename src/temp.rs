@@ -0,0 +1,93 @@
+use std::collections::hash_map::RandomState;
+use std::fs::DirBuilder;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::hash::BuildHasher;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// How many names are tried before giving up.
+const ATTEMPTS: usize = 100;
+
+/// Creates a new file named `{prefix}-{random}{suffix}` in the temporary
+/// directory, readable only by the user. The file is created exclusively, so
+/// a file or symlink placed there by someone else is never written to.
+pub fn create_file(prefix: &str, suffix: &str) -> Result<(PathBuf, File), Error> {
+    create(prefix, suffix, |path| {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(path)
+    })
+}
+
+/// Creates a new directory named `{prefix}-{random}` in the temporary
+/// directory, accessible only by the user.
+pub fn create_dir(prefix: &str) -> Result<PathBuf, Error> {
+    let (path, ()) = create(prefix, "", |path| {
+        let mut builder = DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(path)
+    })?;
+    Ok(path)
+}
+
+fn create<T>(
+    prefix: &str,
+    suffix: &str,
+    mut create: impl FnMut(&PathBuf) -> Result<T, Error>,
+) -> Result<(PathBuf, T), Error> {
+    let dir = std::env::temp_dir();
+    for _ in 0..ATTEMPTS {
+        let path = dir.join(format!("{prefix}-{:016x}{suffix}", random()));
+        match create(&path) {
+            Ok(created) => return Ok((path, created)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(Error::other(format!(
+        "Failed to create a temporary file in {}",
+        dir.display()
+    )))
+}
+
+/// Returns a number which is hard to predict, from the random keys the
+/// standard library seeds hash maps with, which differ for every call.
+fn random() -> u64 {
+    RandomState::new().hash_one(std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_random_and_creation_exclusive() {
+        let (first, _) = create_file("cargo-gpt-test", ".md").unwrap();
+        let (second, _) = create_file("cargo-gpt-test", ".md").unwrap();
+        assert_ne!(first, second);
+        assert!(first.to_string_lossy().ends_with(".md"));
+        let error = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&first)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        let dir = create_dir("cargo-gpt-test").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&first), 0o600);
+            assert_eq!(mode(&dir), 0o700);
+        }
+        for path in [&first, &second] {
+            std::fs::remove_file(path).unwrap();
+        }
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
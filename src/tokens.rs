@@ -0,0 +1,5 @@
+/// Estimates the number of tokens `text` occupies in a model's context, using
+/// the rule of thumb of four characters per token.
+pub fn estimate(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
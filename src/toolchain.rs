@@ -0,0 +1,28 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::doctor;
+
+/// Returns a `cargo` command running in `dir` with a nightly rustc, which
+/// unstable `-Z` flags need: the toolchain selected for `dir` if it is a
+/// nightly, or else the `nightly` toolchain of rustup if it is installed.
+pub fn nightly_cargo(dir: &Path) -> Option<Command> {
+    let selected = doctor::version(Command::new("rustc").arg("-V").current_dir(dir))?;
+    let mut command = if is_nightly(&selected) {
+        Command::new("cargo")
+    } else {
+        doctor::version(Command::new("rustup").args(["run", "nightly", "rustc", "-V"]))?;
+        let mut command = Command::new("rustup");
+        command.args(["run", "nightly", "cargo"]);
+        command
+    };
+    command.current_dir(dir);
+    Some(command)
+}
+
+/// Returns true if `version`, as printed by `rustc -V`, is of a nightly or a
+/// locally built compiler, which accept unstable flags.
+fn is_nightly(version: &str) -> bool {
+    let release = version.split_whitespace().nth(1).unwrap_or("");
+    release.ends_with("-nightly") || release.ends_with("-dev")
+}
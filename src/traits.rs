@@ -0,0 +1,195 @@
+use std::collections::BTreeSet;
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use ignore::WalkBuilder;
+
+use crate::annotate;
+use crate::cli::Args;
+use crate::config::Config;
+use crate::dump;
+use crate::elide;
+use crate::elide::ItemKind;
+use crate::manifest::Tracked;
+use crate::project::Package;
+use crate::project::Project;
+
+/// Writes the definitions and documentation of the traits of dependencies
+/// which are implemented in the project, taken from the sources of the
+/// dependencies, with the bodies of default methods elided.
+pub fn write_foreign_traits(
+    project: &Project,
+    args: &Args,
+    config: &Config,
+    writer: &mut Tracked<impl Write>,
+) -> Result<(), Error> {
+    // The source directories of the dependencies, the crates and the names of the traits.
+    let mut traits = BTreeSet::new();
+    for file in dump::project_files(project, args) {
+        if file.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let Some(package) = project.package_of(&file) else {
+            continue;
+        };
+        let source = std::fs::read_to_string(&file)?;
+        for implemented in elide::impls(&source)
+            .into_iter()
+            .filter_map(|i| i.implemented)
+        {
+            if let Some((krate, name)) = resolve(&implemented, &source, package) {
+                traits.insert((package.sources[&krate].clone(), krate, name));
+            }
+        }
+    }
+    for (dir, krate, name) in traits {
+        let Some((file, definition)) = definition(&dir, &name)? else {
+            writeln!(
+                writer,
+                "// The definition of `{krate}::{name}` was not found in {}",
+                dir.display()
+            )?;
+            continue;
+        };
+        let definition = elide::elide_bodies(&definition, config.elision_marker(), |_| false);
+        // Relative to the directory containing the dependency, such as
+        // `serde-1.0.210/src/ser/mod.rs`.
+        let base = dir.parent().and_then(Path::parent).unwrap_or(&dir);
+        let header = dump::slashed(&dump::relative(&file, base));
+        let template = config.file_header(args.format);
+        let bytes = std::fs::read(&file)?;
+        writer.section(&header, &file, &bytes, &definition, |writer, _| {
+            args.format
+                .write_file(template, &header, &definition, writer)
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the crate and name of the trait at `path` in an `impl` block of
+/// `source`, if it is defined by a dependency of `package`. Traits imported
+/// by a `use` statement are resolved through it.
+fn resolve(path: &str, source: &str, package: &Package) -> Option<(String, String)> {
+    let segments = path
+        .split("::")
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let name = segments.last()?.to_string();
+    let first = segments[0];
+    if segments.len() > 1 && package.sources.contains_key(first) {
+        return Some((first.to_string(), name));
+    }
+    // The crate of a `use` statement importing the first segment, which may
+    // span several lines.
+    let krate = source.split(';').find_map(|statement| {
+        let start = statement
+            .lines()
+            .position(|line| annotate::used_crate(line).is_some())?;
+        let statement = statement.lines().skip(start).collect::<Vec<_>>().join(" ");
+        let imports = statement
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .any(|word| word == first);
+        annotate::used_crate(&statement)
+            .filter(|krate| imports && package.sources.contains_key(*krate))
+            .map(str::to_string)
+    })?;
+    Some((krate, name))
+}
+
+/// Finds the public trait `name` in the Rust files under `dir`, returning the
+/// file and the source of the trait with its documentation.
+fn definition(dir: &Path, name: &str) -> Result<Option<(PathBuf, String)>, Error> {
+    let mut files = WalkBuilder::new(dir)
+        .build()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .collect::<Vec<_>>();
+    files.sort();
+    for file in files {
+        let source = std::fs::read_to_string(&file)?;
+        let extracted = elide::extract(&source, |item| {
+            item.kind == ItemKind::Trait && item.public && item.name == name
+        });
+        if !extracted.is_empty() {
+            return Ok(Some((file, extracted)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn package(fixture: &Fixture) -> Package {
+        let mut package = Project::quick(&fixture.dir).packages.remove(0);
+        for krate in ["serde", "tokio"] {
+            package
+                .sources
+                .insert(krate.to_string(), fixture.dir.join(krate));
+        }
+        package
+    }
+
+    fn resolved(path: &str, source: &str, package: &Package) -> Option<String> {
+        resolve(path, source, package).map(|(krate, name)| format!("{krate}::{name}"))
+    }
+
+    #[test]
+    fn paths_are_resolved_to_dependencies() {
+        let fixture = Fixture::new();
+        let package = package(&fixture);
+        let resolved = |path| resolved(path, "", &package);
+        assert_eq!(
+            resolved("serde::Serialize").as_deref(),
+            Some("serde::Serialize")
+        );
+        assert_eq!(
+            resolved("::serde::ser::Serialize").as_deref(),
+            Some("serde::Serialize")
+        );
+        assert_eq!(resolved("std::fmt::Display"), None);
+        assert_eq!(resolved("crate::Visit"), None);
+        assert_eq!(resolved("Serialize"), None);
+    }
+
+    #[test]
+    fn imported_traits_are_resolved_through_their_use() {
+        let fixture = Fixture::new();
+        let package = package(&fixture);
+        let source =
+            "use std::fmt;\nuse serde::{\n    Deserialize,\n    Serialize,\n};\nuse tokio::io;\n";
+        assert_eq!(
+            resolved("Serialize", source, &package).as_deref(),
+            Some("serde::Serialize")
+        );
+        assert_eq!(
+            resolved("io::AsyncRead", source, &package).as_deref(),
+            Some("tokio::AsyncRead")
+        );
+        assert_eq!(resolved("fmt::Display", source, &package), None);
+        // Only whole words of the `use` statement import a name.
+        assert_eq!(resolved("Serial", source, &package), None);
+        assert_eq!(resolved("Visit", source, &package), None);
+    }
+
+    #[test]
+    fn definitions_are_found_by_name_and_visibility() {
+        let fixture = Fixture::new();
+        fixture.write("serde/src/lib.rs", "trait Serialize {}\n");
+        fixture.write(
+            "serde/src/ser/mod.rs",
+            "/// Serializes.\npub trait Serialize {\n    fn serialize(&self) {\n        todo!()\n    }\n}\n",
+        );
+        let (file, extracted) = definition(&fixture.dir.join("serde"), "Serialize")
+            .unwrap()
+            .unwrap();
+        assert_eq!(file, fixture.dir.join("serde/src/ser/mod.rs"));
+        assert!(extracted.starts_with("/// Serializes.\npub trait Serialize {"));
+        assert!(definition(&fixture.dir, "Missing").unwrap().is_none());
+    }
+}
@@ -0,0 +1,18 @@
+use std::io::Error;
+use std::io::Read;
+use std::io::Write;
+
+use crate::cli::TransformArgs;
+use crate::config::Config;
+use crate::elide;
+
+pub fn run(args: &TransformArgs, config: &Config, writer: &mut impl Write) -> Result<(), Error> {
+    let mut source = String::new();
+    std::io::stdin().read_to_string(&mut source)?;
+    let transformed = if args.elide_all {
+        elide::elide_bodies(&source, config.elision_marker(), |_| false)
+    } else {
+        elide::extract(&source, |item| args.only.iter().any(|p| item.matches(p)))
+    };
+    writer.write_all(transformed.as_bytes())
+}
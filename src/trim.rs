@@ -0,0 +1,254 @@
+use std::io::BufRead;
+use std::io::Error;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::dump;
+use crate::elide;
+use crate::manifest::Tracked;
+use crate::project::Project;
+use crate::tokens;
+
+/// Number of the largest files offered for trimming at a time.
+const SHOWN: usize = 10;
+
+/// A file of the dump with its estimated size as it would be dumped.
+struct Candidate {
+    /// Index of the project in the dump.
+    project: usize,
+    file: PathBuf,
+    header: String,
+    tokens: usize,
+    elided: bool,
+}
+
+/// Shows the largest files of the dump on the terminal while it is larger
+/// than `budget` tokens, and lets the user elide their function bodies or
+/// exclude them until it fits. The choices are recorded in the projects.
+pub fn interactive(
+    projects: &mut [Project],
+    args: &Args,
+    config: &Config,
+    budget: usize,
+) -> Result<(), Error> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Err(Error::other(
+            "--interactive reads the choices from the terminal, but standard input is not one",
+        ));
+    }
+    trim(
+        projects,
+        args,
+        config,
+        budget,
+        &mut stdin.lock(),
+        &mut std::io::stderr(),
+    )
+}
+
+/// Runs the choices of [`interactive`], read from `input` with the prompts
+/// written to `output`.
+fn trim(
+    projects: &mut [Project],
+    args: &Args,
+    config: &Config,
+    budget: usize,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<(), Error> {
+    let mut candidates = Vec::new();
+    for (i, project) in projects.iter().enumerate() {
+        for file in dump::project_files(project, args) {
+            candidates.push(Candidate {
+                project: i,
+                header: project.header_path(&file),
+                tokens: size(project, &file, args, config)?,
+                file,
+                elided: false,
+            });
+        }
+    }
+    loop {
+        let total = candidates.iter().map(|c| c.tokens).sum::<usize>();
+        if total <= budget {
+            writeln!(output, "The dump is ~{total} tokens and fits in {budget}.")?;
+            break;
+        }
+        sort(&mut candidates);
+        writeln!(
+            output,
+            "The dump is ~{total} tokens, {} over the budget of {budget}. The largest files:",
+            total - budget
+        )?;
+        for (n, candidate) in candidates.iter().take(SHOWN).enumerate() {
+            let elided = if candidate.elided { " (elided)" } else { "" };
+            writeln!(
+                output,
+                "{:>3}. {:>7}  {}{elided}",
+                n + 1,
+                candidate.tokens,
+                candidate.header
+            )?;
+        }
+        write!(
+            output,
+            "Elide or exclude files by number (e.g. `e 1 3` or `x 2`), or `d` to dump as is: "
+        )?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        if command == "d" {
+            break;
+        }
+        let mut picked = Vec::new();
+        for word in words {
+            match word.parse::<usize>() {
+                Ok(n) if (1..=SHOWN.min(candidates.len())).contains(&n) => picked.push(n - 1),
+                _ => writeln!(output, "No file numbered `{word}`.")?,
+            }
+        }
+        match command {
+            "e" => {
+                for &n in &picked {
+                    let candidate = &mut candidates[n];
+                    if candidate.file.extension().is_none_or(|ext| ext != "rs") {
+                        writeln!(output, "Only Rust files can be elided.")?;
+                        continue;
+                    }
+                    if candidate.elided {
+                        continue;
+                    }
+                    let project = &mut projects[candidate.project];
+                    let source =
+                        String::from_utf8_lossy(&std::fs::read(&candidate.file)?).into_owned();
+                    let functions = elide::items(&source)
+                        .into_iter()
+                        .filter(|item| item.body.is_some())
+                        .map(|item| (candidate.file.clone(), item.path()));
+                    project.elided.extend(functions);
+                    candidate.tokens = size(project, &candidate.file, args, config)?;
+                    candidate.elided = true;
+                }
+            }
+            "x" => {
+                picked.sort_unstable_by(|a, b| b.cmp(a));
+                picked.dedup();
+                for n in picked {
+                    let candidate = candidates.remove(n);
+                    projects[candidate.project].excluded.push(candidate.file);
+                }
+            }
+            _ => writeln!(output, "Expected `e`, `x` or `d`, found `{command}`.")?,
+        }
+    }
+    Ok(())
+}
+
+/// Orders the candidates by size, the largest first, and then by path.
+fn sort(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.file.cmp(&b.file)));
+}
+
+/// Returns the estimated tokens of the section of `file` as it would be
+/// dumped, with its header and the elisions chosen so far.
+fn size(project: &Project, file: &Path, args: &Args, config: &Config) -> Result<usize, Error> {
+    let mut section = Tracked::new(Vec::new());
+    dump::read_file(file, project, args, config, &mut section)?;
+    Ok(tokens::estimate(&String::from_utf8_lossy(
+        &section.into_inner(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use crate::fixture::Fixture;
+
+    /// A function whose body is many times the size of its signature.
+    fn function(name: &str, lines: usize) -> String {
+        let body = "    let x = 1 + 2 + 3 + 4 + 5 + 6 + 7 + 8;\n".repeat(lines);
+        format!("pub fn {name}() {{\n{body}}}\n")
+    }
+
+    fn candidate(file: &str, tokens: usize) -> Candidate {
+        Candidate {
+            project: 0,
+            file: PathBuf::from(file),
+            header: file.to_string(),
+            tokens,
+            elided: false,
+        }
+    }
+
+    #[test]
+    fn candidates_are_ordered_by_size_then_path() {
+        let mut candidates = vec![
+            candidate("src/b.rs", 10),
+            candidate("src/c.rs", 30),
+            candidate("src/a.rs", 10),
+        ];
+        sort(&mut candidates);
+        let files = candidates
+            .iter()
+            .map(|c| c.header.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["src/c.rs", "src/a.rs", "src/b.rs"]);
+    }
+
+    #[test]
+    fn sizes_are_those_of_the_rendered_sections() {
+        let fixture = Fixture::new();
+        let file = fixture.write("src/rows.csv", "n\n".repeat(10000));
+        let project = Project::quick(&fixture.dir);
+        let args = fixture::args(&["--data"]);
+        let tokens = size(&project, &file, &args, &Config::default()).unwrap();
+        assert!((1..50).contains(&tokens), "{tokens}");
+    }
+
+    #[test]
+    fn files_are_elided_and_excluded_until_the_dump_fits() {
+        let fixture = Fixture::new();
+        let large = fixture.write("src/large.rs", function("large", 40));
+        let medium = fixture.write("src/medium.rs", function("medium", 20));
+        fixture.write("src/small.rs", function("small", 1));
+        let mut projects = vec![Project::quick(&fixture.dir)];
+        let args = fixture::args(&[]);
+        let mut input = "e 1\nx 1\n".as_bytes();
+        let mut output = Vec::new();
+        trim(
+            &mut projects,
+            &args,
+            &Config::default(),
+            100,
+            &mut input,
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let prompts = output
+            .lines()
+            .filter(|line| line.contains("The largest files:"))
+            .count();
+        assert_eq!(prompts, 2, "{output}");
+        assert!(output.contains("  1. "), "{output}");
+        assert!(output.ends_with("fits in 100.\n"), "{output}");
+        // After eliding the largest file, the medium one is the largest.
+        let second = output.split("The largest files:").nth(2).unwrap();
+        assert!(
+            second.lines().nth(1).unwrap().ends_with("src/medium.rs"),
+            "{output}"
+        );
+        assert_eq!(projects[0].elided, [(large, "large".to_string())]);
+        assert_eq!(projects[0].excluded, [medium]);
+    }
+}